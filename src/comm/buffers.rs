@@ -0,0 +1,54 @@
+use anyhow::{Context, Result};
+use bytes::Bytes;
+use serde_json::Value;
+
+/// Where a binary buffer belongs in the data of a comm message: a list of object keys (strings)
+/// and array indices (numbers). Widgets give paths from the top level of their state.
+///
+/// JSON cannot hold binary data, so binary values are removed from the data before it is sent,
+/// and the message lists the path of each one in `buffer_paths`, in the same order as the binary
+/// buffers attached to the message. See [Comm::send_with_buffer_paths](super::Comm::send_with_buffer_paths).
+pub type BufferPath = Vec<Value>;
+
+/// Pair each path in the `buffer_paths` of a message with the buffer it describes
+pub fn zip_buffer_paths(buffer_paths: Option<&Value>, buffers: &[Bytes]) -> Result<Vec<(BufferPath, Bytes)>> {
+    let Some(buffer_paths) = buffer_paths else {
+        return Ok(Vec::new());
+    };
+    let buffer_paths: Vec<BufferPath> = serde_json::from_value(buffer_paths.clone())
+        .context("buffer_paths must be a list of lists")?;
+    anyhow::ensure!(
+        buffer_paths.len() <= buffers.len(),
+        "The message has {} buffer_paths but only {} buffers",
+        buffer_paths.len(),
+        buffers.len(),
+    );
+    Ok(buffer_paths.into_iter().zip(buffers.iter().cloned()).collect())
+}
+
+/// Split paired paths and buffers into the `buffer_paths` of a message and its buffers
+pub fn unzip_buffer_paths(entries: &[(BufferPath, Bytes)]) -> (Value, Vec<Bytes>) {
+    let (buffer_paths, buffers): (Vec<Value>, Vec<Bytes>) = entries
+        .iter()
+        .map(|(path, buffer)| (Value::Array(path.clone()), buffer.clone()))
+        .unzip();
+    (Value::Array(buffer_paths), buffers)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_buffer_paths_round_trip() {
+        let buffers = vec![Bytes::from_static(b"a"), Bytes::from_static(b"b")];
+        let paths = json!([["value"], ["data", 0, "x"]]);
+        let entries = zip_buffer_paths(Some(&paths), &buffers).unwrap();
+        assert_eq!(entries[1], (vec![json!("data"), json!(0), json!("x")], Bytes::from_static(b"b")));
+        assert_eq!(unzip_buffer_paths(&entries), (paths, buffers.clone()));
+
+        assert!(zip_buffer_paths(None, &buffers).unwrap().is_empty());
+        assert!(zip_buffer_paths(Some(&json!([["a"], ["b"], ["c"]])), &buffers).is_err());
+    }
+}
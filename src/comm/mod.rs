@@ -0,0 +1,431 @@
+//! Comms are channels for custom messages between code running in the kernel and code running
+//! in the frontend, e.g. the two halves of an interactive widget. See
+//! [Custom Messages](https://jupyter-client.readthedocs.io/en/latest/messaging.html#custom-messages)
+//!
+//! The [Comms] registry keeps track of every open comm. A comm opened by the frontend is handed
+//! to the [CommTarget] registered for its `target_name`, which decides how to handle its messages.
+//! The kernel can also open comms itself with [Comms::open].
+
+mod buffers;
+
+pub use buffers::{zip_buffer_paths, unzip_buffer_paths, BufferPath};
+
+use crate::{
+    protocol::{
+        CommClose,
+        CommInfo,
+        CommMsg,
+        CommOpen,
+        Header,
+        Publication,
+    },
+    server::Iopub,
+    util::EmptyObjectOr,
+};
+
+use anyhow::{Context, Result};
+use bytes::Bytes;
+use serde_json::{Map, Value};
+use std::{
+    collections::HashMap,
+    future::Future,
+    sync::{Arc, Mutex},
+};
+use tracing::debug;
+use uuid::Uuid;
+
+/// Handles the messages sent by the frontend to one open comm
+pub trait CommHandler: Send + Sync + 'static {
+    /// Called for each `comm_msg` sent by the frontend, along with any binary buffers attached to
+    /// the message
+    fn on_message(&self, comm: &Comm, data: &Map<String, Value>, buffers: &[Bytes]) -> Result<()>;
+
+    /// Called when the frontend closes the comm
+    fn on_close(&self, _comm: &Comm, _data: &Map<String, Value>) -> Result<()> {
+        Ok(())
+    }
+}
+
+/// Creates a [CommHandler] for each comm the frontend opens with a particular `target_name`.
+///
+/// Implemented for any closure taking the new [Comm], and the `data` and binary buffers of the
+/// `comm_open`. If it fails, the comm is closed again straight away.
+pub trait CommTarget: Send + Sync + 'static {
+    fn open(&self, comm: &Comm, data: &Value, buffers: &[Bytes]) -> Result<Arc<dyn CommHandler>>;
+}
+
+impl<F> CommTarget for F
+where
+    F: Fn(&Comm, &Value, &[Bytes]) -> Result<Arc<dyn CommHandler>> + Send + Sync + 'static,
+{
+    fn open(&self, comm: &Comm, data: &Value, buffers: &[Bytes]) -> Result<Arc<dyn CommHandler>> {
+        self(comm, data, buffers)
+    }
+}
+
+/// One end of an open comm, used to send messages to the frontend
+#[derive(Clone)]
+pub struct Comm {
+    id: String,
+    target_name: String,
+    comms: Comms,
+}
+
+impl std::fmt::Debug for Comm {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("Comm")
+            .field("id", &self.id)
+            .field("target_name", &self.target_name)
+            .finish()
+    }
+}
+
+impl Comm {
+    pub fn id(&self) -> &str {
+        &self.id
+    }
+
+    pub fn target_name(&self) -> &str {
+        &self.target_name
+    }
+
+    /// Send a `comm_msg` to the frontend. `data` must be a json object.
+    pub fn send(&self, data: Value) -> Result<()> {
+        self.send_with_buffers(data, Vec::new())
+    }
+
+    /// Send a `comm_msg` to the frontend with binary buffers attached
+    pub fn send_with_buffers(&self, data: Value, buffers: Vec<Bytes>) -> Result<()> {
+        self.comms.publish(
+            CommMsg {
+                comm_id: self.id.clone(),
+                data: into_object(data)?,
+            },
+            Default::default(),
+            buffers,
+        )
+    }
+
+    /// Send a `comm_msg` to the frontend with binary buffers attached, adding the `buffer_paths`
+    /// which say where each buffer belongs to `data`. Frontends put the buffers back in place
+    /// before handing the message on, so large arrays or images need not be base64 encoded.
+    ///
+    /// Use [zip_buffer_paths] on the `buffer_paths` and buffers of a received message to do the
+    /// reverse.
+    pub fn send_with_buffer_paths(&self, data: Value, buffers: &[(BufferPath, Bytes)]) -> Result<()> {
+        let mut data = into_object(data)?;
+        let (buffer_paths, buffers) = unzip_buffer_paths(buffers);
+        data.insert("buffer_paths".into(), buffer_paths);
+        self.send_with_buffers(Value::Object(data), buffers)
+    }
+
+    /// Close the comm; the frontend is sent a `comm_close` and the handler is dropped
+    pub fn close(&self, data: Value) -> Result<()> {
+        self.comms.inner.open.lock().unwrap().remove(&self.id);
+        self.comms.publish(
+            CommClose {
+                comm_id: self.id.clone(),
+                data: into_object(data)?,
+            },
+            Default::default(),
+            Vec::new(),
+        )
+    }
+}
+
+fn into_object(data: Value) -> Result<Map<String, Value>> {
+    match data {
+        Value::Object(map) => Ok(map),
+        Value::Null => Ok(Map::new()),
+        other => anyhow::bail!("The data of a comm message must be a json object, not {other}"),
+    }
+}
+
+tokio::task_local! {
+    /// The request being handled by the current task; messages sent on comms are children of it.
+    /// Task local, since the main shell and each subshell handle their requests concurrently.
+    static PARENT_HEADER: EmptyObjectOr<Header>;
+}
+
+struct OpenComm {
+    comm: Comm,
+    handler: Arc<dyn CommHandler>,
+}
+
+struct Inner {
+    iopub: Iopub,
+    /// The request handled most recently, which messages sent on comms from outside of
+    /// [Comms::in_reply_to], e.g. from a task spawned by the kernel, are children of
+    latest_parent_header: Mutex<EmptyObjectOr<Header>>,
+    targets: Mutex<HashMap<String, Arc<dyn CommTarget>>>,
+    open: Mutex<HashMap<String, OpenComm>>,
+}
+
+/// The registry of comm targets and open comms. Cheap to clone; all clones share the same state.
+#[derive(Clone)]
+pub struct Comms {
+    inner: Arc<Inner>,
+}
+
+impl std::fmt::Debug for Comms {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("Comms")
+            .field("targets", &self.inner.targets.lock().unwrap().keys().collect::<Vec<_>>())
+            .field("open", &self.inner.open.lock().unwrap().keys().collect::<Vec<_>>())
+            .finish()
+    }
+}
+
+impl Comms {
+    pub(crate) fn new(iopub: Iopub) -> Self {
+        Self {
+            inner: Arc::new(Inner {
+                iopub,
+                latest_parent_header: Mutex::new(Default::default()),
+                targets: Default::default(),
+                open: Default::default(),
+            }),
+        }
+    }
+
+    /// Handle comms opened by the frontend with `target_name` using `target`, replacing any target
+    /// previously registered under that name
+    pub fn register_target(&self, target_name: &str, target: impl CommTarget) {
+        self.inner.targets.lock().unwrap().insert(target_name.to_owned(), Arc::new(target));
+    }
+
+    /// Stop accepting new comms for `target_name`. Comms which are already open are not affected.
+    pub fn unregister_target(&self, target_name: &str) {
+        self.inner.targets.lock().unwrap().remove(target_name);
+    }
+
+    /// Open a comm from the kernel side. The frontend is sent a `comm_open` with `data`, and its
+    /// messages on the comm are passed to `handler`.
+    pub fn open(&self, target_name: &str, data: Value, handler: impl CommHandler) -> Result<Comm> {
+        self.open_with(target_name, data, Default::default(), Vec::new(), handler)
+    }
+
+    /// Like [Comms::open], also setting the `metadata` of the `comm_open` message and attaching
+    /// binary buffers to it
+    pub fn open_with(
+        &self,
+        target_name: &str,
+        data: Value,
+        metadata: Map<String, Value>,
+        buffers: Vec<Bytes>,
+        handler: impl CommHandler,
+    ) -> Result<Comm> {
+        let comm = Comm {
+            id: Uuid::new_v4().simple().to_string(),
+            target_name: target_name.to_owned(),
+            comms: self.clone(),
+        };
+        self.inner.open.lock().unwrap().insert(comm.id.clone(), OpenComm {
+            comm: comm.clone(),
+            handler: Arc::new(handler),
+        });
+        self.publish(
+            CommOpen {
+                comm_id: comm.id.clone(),
+                target_name: target_name.to_owned(),
+                data,
+                target_module: None,
+            },
+            metadata,
+            buffers,
+        )?;
+        Ok(comm)
+    }
+
+    /// Look up an open comm by its id
+    pub fn get(&self, comm_id: &str) -> Option<Comm> {
+        self.inner.open.lock().unwrap().get(comm_id).map(|open| open.comm.clone())
+    }
+
+    /// The open comms, optionally only those with the given `target_name`, as needed for a
+    /// `comm_info_reply`
+    pub fn info(&self, target_name: Option<&str>) -> HashMap<String, CommInfo> {
+        self.inner.open.lock().unwrap()
+            .iter()
+            .filter(|(_, open)| target_name.is_none_or(|target_name| open.comm.target_name == target_name))
+            .map(|(comm_id, open)| (comm_id.clone(), CommInfo {
+                target_name: open.comm.target_name.clone(),
+            }))
+            .collect()
+    }
+
+    fn publish(
+        &self,
+        content: impl Publication,
+        metadata: Map<String, Value>,
+        buffers: Vec<Bytes>,
+    ) -> Result<()> {
+        let parent_header = PARENT_HEADER
+            .try_with(Clone::clone)
+            .unwrap_or_else(|_| self.inner.latest_parent_header.lock().unwrap().clone());
+        self.inner.iopub.publish_with_buffers(parent_header, content, metadata, buffers)
+    }
+
+    /// Run `future`, making any messages it sends on comms children of `parent_header`
+    pub(crate) async fn in_reply_to<F: Future>(&self, parent_header: EmptyObjectOr<Header>, future: F) -> F::Output {
+        *self.inner.latest_parent_header.lock().unwrap() = parent_header.clone();
+        PARENT_HEADER.scope(parent_header, future).await
+    }
+
+    /// Forget every open comm, e.g. when the kernel restarts
+    pub(crate) fn clear(&self) {
+        self.inner.open.lock().unwrap().clear();
+    }
+
+    /// Forget every open comm and every target, once the kernel has shut down. Handlers and
+    /// targets often hold a clone of the registry, so this breaks the reference cycle which would
+    /// otherwise keep the iopub task waiting forever.
+    pub(crate) fn close_all(&self) {
+        self.clear();
+        self.inner.targets.lock().unwrap().clear();
+    }
+
+    /// Handle a `comm_open` from the frontend. If there is no target registered for the
+    /// `target_name`, or the target fails, the comm is closed again immediately as required by
+    /// [Opening a comm](https://jupyter-client.readthedocs.io/en/latest/messaging.html#opening-a-comm)
+    pub(crate) fn handle_open(&self, comm_open: &CommOpen, buffers: &[Bytes]) -> Result<()> {
+        let comm = Comm {
+            id: comm_open.comm_id.clone(),
+            target_name: comm_open.target_name.clone(),
+            comms: self.clone(),
+        };
+        let target = self.inner.targets.lock().unwrap().get(&comm_open.target_name).cloned();
+        let handler = match target {
+            Some(target) => target.open(&comm, &comm_open.data, buffers),
+            None => Err(anyhow::anyhow!("No comm target registered for {:?}", comm_open.target_name)),
+        };
+        match handler {
+            Ok(handler) => {
+                self.inner.open.lock().unwrap().insert(comm.id.clone(), OpenComm { comm, handler });
+                Ok(())
+            },
+            Err(err) => {
+                println_debug!("Rejecting comm_open: {err:?}");
+                comm.close(Value::Null)
+            },
+        }
+    }
+
+    /// Pass a `comm_msg` from the frontend to the handler of the comm
+    pub(crate) fn handle_message(&self, comm_msg: &CommMsg, buffers: &[Bytes]) -> Result<()> {
+        let (comm, handler) = self.open_comm(&comm_msg.comm_id)?;
+        handler.on_message(&comm, &comm_msg.data, buffers)
+    }
+
+    /// Remove a comm closed by the frontend, and let its handler know
+    pub(crate) fn handle_close(&self, comm_close: &CommClose) -> Result<()> {
+        let (comm, handler) = self.open_comm(&comm_close.comm_id)?;
+        self.inner.open.lock().unwrap().remove(&comm_close.comm_id);
+        handler.on_close(&comm, &comm_close.data)
+    }
+
+    /// Clone the comm and its handler out of the registry, so that the lock is not held while the
+    /// handler runs; handlers are free to open or close other comms
+    fn open_comm(&self, comm_id: &str) -> Result<(Comm, Arc<dyn CommHandler>)> {
+        self.inner.open.lock().unwrap()
+            .get(comm_id)
+            .map(|open| (open.comm.clone(), open.handler.clone()))
+            .with_context(|| format!("No open comm with id {comm_id:?}"))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{protocol::MessageType, server::Session};
+    use serde_json::json;
+
+    struct Ignore;
+
+    impl CommHandler for Ignore {
+        fn on_message(&self, _comm: &Comm, _data: &Map<String, Value>, _buffers: &[Bytes]) -> Result<()> {
+            Ok(())
+        }
+    }
+
+    fn comm_open(comm_id: &str, target_name: &str) -> CommOpen {
+        CommOpen {
+            comm_id: comm_id.into(),
+            target_name: target_name.into(),
+            data: json!({}),
+            target_module: None,
+        }
+    }
+
+    #[test]
+    fn test_comm_registry() {
+        let (iopub, mut published) = Iopub::new(Session::new(Default::default()), Default::default());
+        let comms = Comms::new(iopub);
+        comms.register_target("a", |_: &Comm, _: &Value, _: &[Bytes]| Ok(Arc::new(Ignore) as Arc<dyn CommHandler>));
+
+        comms.handle_open(&comm_open("1", "a"), &[]).unwrap();
+        let kernel_comm = comms.open("b", json!({}), Ignore).unwrap();
+        assert_eq!(comms.info(None).len(), 2);
+        assert_eq!(comms.info(Some("b")).keys().collect::<Vec<_>>(), vec![kernel_comm.id()]);
+        assert_eq!(published.try_recv().unwrap().header.message_type, MessageType::CommOpen);
+
+        // unknown targets are closed straight away
+        comms.handle_open(&comm_open("2", "unknown"), &[]).unwrap();
+        let close = published.try_recv().unwrap();
+        assert_eq!(close.content, CommClose { comm_id: "2".into(), data: Map::new() }.into());
+        assert!(comms.get("2").is_none());
+
+        comms.handle_close(&CommClose { comm_id: "1".into(), data: Map::new() }).unwrap();
+        kernel_comm.close(Value::Null).unwrap();
+        assert!(comms.info(None).is_empty());
+        assert!(comms.handle_message(&CommMsg { comm_id: "1".into(), data: Map::new() }, &[]).is_err());
+    }
+
+    #[test]
+    fn test_send_with_buffer_paths() {
+        let (iopub, mut published) = Iopub::new(Session::new(Default::default()), Default::default());
+        let comms = Comms::new(iopub);
+        let comm = comms.open("a", json!({}), Ignore).unwrap();
+        published.try_recv().unwrap();
+
+        let buffer = Bytes::from_static(b"\x00\x01");
+        comm.send_with_buffer_paths(json!({"array": {"shape": [2]}}), &[(vec![json!("array"), json!("data")], buffer.clone())]).unwrap();
+        let message = published.try_recv().unwrap();
+        let expected = json!({"array": {"shape": [2]}, "buffer_paths": [["array", "data"]]});
+        assert_eq!(message.content, CommMsg { comm_id: comm.id().into(), data: into_object(expected).unwrap() }.into());
+        assert_eq!(message.extra_buffers, vec![buffer]);
+    }
+
+    #[tokio::test]
+    async fn test_parent_header_per_request() {
+        let session = Session::new(Default::default());
+        let (iopub, mut published) = Iopub::new(session.clone(), Default::default());
+        let comms = Comms::new(iopub);
+        let comm = comms.open("a", json!({}), Ignore).unwrap();
+        published.try_recv().unwrap();
+        let mut parent_id = || Option::from(published.try_recv().unwrap().parent_header).map(|header: Header| header.message_id);
+
+        // the second request sends while the first is still being handled, as happens with subshells
+        let (first, second) = (session.header(MessageType::CommMsg), session.header(MessageType::CommMsg));
+        let (first_sent, wait_for_first) = tokio::sync::oneshot::channel();
+        let (first_result, second_result) = tokio::join!(
+            comms.in_reply_to(first.clone().into(), async {
+                wait_for_first.await.unwrap();
+                comm.send(json!({}))
+            }),
+            comms.in_reply_to(second.clone().into(), async {
+                let result = comm.send(json!({}));
+                first_sent.send(()).unwrap();
+                result
+            }),
+        );
+        first_result.unwrap();
+        second_result.unwrap();
+        assert_eq!(parent_id(), Some(second.message_id.clone()));
+        assert_eq!(parent_id(), Some(first.message_id));
+
+        // outside of any request, messages belong to the latest one
+        comm.send(json!({})).unwrap();
+        assert_eq!(parent_id(), Some(second.message_id));
+    }
+}
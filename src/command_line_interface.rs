@@ -1,5 +1,8 @@
 use clio::Input;
 use clap::Parser;
+use nikkerish::server::IopubTopics;
+
+use crate::install::InterruptMode;
 
 #[derive(Debug, Parser)]
 #[command(author, version, about, long_about = None)]
@@ -11,8 +14,22 @@ pub enum CommandLineInterface {
         /// This is a json file that contains the ip address, ports and other connection metadata.
         #[arg(long)]
         connection_file: Input,
+        /// Only keep the history of executed cells in memory, instead of saving it to the Jupyter
+        /// data directory where it is available to later sessions
+        #[arg(long)]
+        no_history: bool,
+        /// The topics messages are published under on iopub; the `msg_type` (`message-type`), the
+        /// convention used by ipykernel (`ipykernel`), or no topic (`none`)
+        #[arg(long, value_enum, default_value_t = IopubTopics::MessageType)]
+        iopub_topics: IopubTopics,
     },
     /// create a new kernel.json and install it by running `jupyter kernelspec install --user [...]`
     #[command()]
-    InstallKernelSpec
+    InstallKernelSpec {
+        /// How the client should interrupt cell execution; by sending SIGINT to the kernel process
+        /// (`signal`) or an `interrupt_request` on the control channel (`message`)
+        // Defaults to message, since vscode just flat out does not seem to play nice with signal
+        #[arg(long, value_enum, default_value_t = InterruptMode::Message)]
+        interrupt_mode: InterruptMode,
+    }
 }
@@ -1,6 +1,7 @@
 use std::{net::IpAddr, fmt::Display};
 use serde::Deserialize;
 use anyhow::Result;
+use crate::protocol::SigningKey;
 use zeromq::Socket;
 use tracing::debug;
 #[derive(Debug, Deserialize)]
@@ -19,10 +20,20 @@ impl Display for Transport {
         }
     }
 }
-#[derive(Debug, Deserialize)]
+/// The HMAC algorithm used to sign messages, see [SigningKey](crate::protocol::SigningKey)
+#[derive(Debug, Deserialize, Default, Clone, Copy, PartialEq)]
 pub enum SignatureScheme{
+    #[serde(rename="hmac-md5")]
+    HmacMd5,
+    #[serde(rename="hmac-sha1")]
+    HmacSha1,
+    #[default]
     #[serde(rename="hmac-sha256")]
-    HmacSha256
+    HmacSha256,
+    #[serde(rename="hmac-sha384")]
+    HmacSha384,
+    #[serde(rename="hmac-sha512")]
+    HmacSha512,
 }
 
 /// Represents the JSON connection file created by the client (eg vscode or jupyter lab) and read by
@@ -32,13 +43,10 @@ pub struct ConnectionInformation {
     /// The IP address of the kernel
     #[serde(rename="ip")]
     pub ip_address: IpAddr,
-    /// the only valid value for `signature_scheme` appears to be `"hmac-sha256"` or perhaps `""`
-    /// is allowed if `key` is also `""`
+    /// The algorithm used to sign messages with `key`, e.g. `"hmac-sha256"`
     pub signature_scheme: SignatureScheme,
     /// Typically a UUID when signature scheme is specified.
     /// To disable message signing, set this to an empty string
-    /// 
-    /// TODO: Probably should be an option and the empty string should be a None variant
     pub key: String,
     /// Either `"tcp"`
     /// [Transmission Control Protocol](https://en.wikipedia.org/wiki/Transmission_Control_Protocol)
@@ -108,6 +116,11 @@ macro_rules! create_socket {
 }
 
 impl ConnectionInformation {
+    /// The key used to sign messages, and to check the signature of messages received
+    pub fn signing_key(&self) -> SigningKey {
+        SigningKey::new(self.signature_scheme, &self.key)
+    }
+
     create_socket!(create_socket_shell    , zeromq::RouterSocket, shell_port    );
     create_socket!(create_socket_iopub    , zeromq::PubSocket   , iopub_port    );
     create_socket!(create_socket_stdin    , zeromq::RouterSocket, stdin_port    );
@@ -0,0 +1,533 @@
+//! Support for JupyterLab's visual debugger, which speaks the
+//! [Debug Adapter Protocol](https://microsoft.github.io/debug-adapter-protocol/specification)
+//! (DAP) to the kernel over `debug_request`/`debug_reply` on the control channel and
+//! `debug_event` on iopub. See [Debug request](https://jupyter-client.readthedocs.io/en/latest/messaging.html#debug-request)
+//!
+//! The frontend dumps each cell to a file with `dumpCell` and sets breakpoints by file path and
+//! line. Since JupyterLab works out the path of a cell itself from the `hashMethod` reported by
+//! `debugInfo`, the kernel must name its cells with [Debugger::source_path] when executing them.
+//!
+//! The [Debugger] answers requests on its own; the kernel only has to call
+//! [ExecutionContext::trace](crate::kernel::ExecutionContext::trace) before running each line.
+//! Execution pauses there while the frontend inspects the stack and variables, until it sends
+//! `continue`, `next` or `stepIn`.
+//!
+//! The main shell and each subshell execute concurrently, so each is a separate DAP thread which
+//! pauses and resumes on its own.
+
+mod murmur2;
+
+use murmur2::murmur2;
+
+use crate::{
+    protocol::{
+        DebugEventPublication,
+        DebugReply,
+        DebugRequest,
+        Header,
+    },
+    server::Iopub,
+    util::EmptyObjectOr,
+};
+
+use anyhow::{Context, Result};
+use serde_json::{json, Value};
+use std::{
+    collections::{BTreeMap, BTreeSet, HashMap},
+    path::{PathBuf, MAIN_SEPARATOR},
+    sync::{
+        atomic::{AtomicU64, Ordering},
+        Arc,
+        Mutex,
+    },
+};
+use tokio::sync::oneshot;
+use tracing::debug;
+
+/// The seed of the hash used to name the file of each cell; the same as ipykernel
+const HASH_SEED: u32 = 3339675911;
+/// The thread executing the requests sent to the main shell; each subshell has a thread of its own
+const MAIN_THREAD_ID: u64 = 1;
+
+/// One frame of the call stack at the point where execution is paused. The innermost frame comes
+/// first.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Frame {
+    /// Shown in the call stack panel, e.g. the name of the function
+    pub name: String,
+    /// The file being executed, see [Debugger::source_path]
+    pub source_path: String,
+    /// Starting from 1
+    pub line: usize,
+    /// The variables in scope in this frame
+    pub variables: Vec<Variable>,
+}
+
+/// A variable shown in the variables panel of the debugger
+#[derive(Debug, Clone, PartialEq)]
+pub struct Variable {
+    pub name: String,
+    /// A short representation of the value
+    pub value: String,
+    /// The name of the type of the value, if the language has one
+    pub type_name: Option<String>,
+}
+
+impl Variable {
+    fn to_json(&self) -> Value {
+        json!({
+            "name": self.name,
+            "value": self.value,
+            "type": self.type_name,
+            "variablesReference": 0,
+        })
+    }
+}
+
+/// Where the execution of one thread is paused
+#[derive(Debug)]
+struct Stopped {
+    frames: Vec<Frame>,
+    /// The id of the first of `frames`; the others follow on from it. Frame ids are unique across
+    /// every paused thread, since `scopes` and `variables` requests do not say which thread they
+    /// are about.
+    first_frame_id: u64,
+    /// Lets execution carry on
+    resume: oneshot::Sender<()>,
+}
+
+#[derive(Debug, Default)]
+struct State {
+    /// Set from `initialize` until `disconnect`. Execution is only ever paused while started.
+    started: bool,
+    /// The lines of each source path which have a breakpoint
+    breakpoints: BTreeMap<String, Vec<usize>>,
+    /// The threads which pause at their next line, after a `next` or `stepIn`
+    stepping: BTreeSet<u64>,
+    /// Keyed by thread id
+    stopped: BTreeMap<u64, Stopped>,
+    /// The last frame id handed out
+    last_frame_id: u64,
+}
+
+#[derive(Debug)]
+struct Inner {
+    iopub: Iopub,
+    /// Where `dumpCell` writes cells
+    directory: PathBuf,
+    /// The file extension of the language, including the dot
+    file_suffix: String,
+    /// Sequence numbers of the DAP messages sent by the kernel
+    seq: AtomicU64,
+    state: Mutex<State>,
+    /// The thread id of each subshell which has executed anything
+    subshell_threads: Mutex<HashMap<String, u64>>,
+}
+
+/// The kernel side of the debugger. Cheap to clone; all clones share the same state.
+#[derive(Debug, Clone)]
+pub struct Debugger {
+    inner: Arc<Inner>,
+}
+
+impl Debugger {
+    /// `file_suffix` is the extension of the files cells are dumped to, e.g. `.py`
+    pub(crate) fn new(iopub: Iopub, file_suffix: &str) -> Self {
+        Self {
+            inner: Arc::new(Inner {
+                iopub,
+                directory: std::env::temp_dir().join(format!("nikkerish_{}", std::process::id())),
+                file_suffix: file_suffix.to_owned(),
+                seq: AtomicU64::new(1),
+                state: Default::default(),
+                subshell_threads: Default::default(),
+            }),
+        }
+    }
+
+    /// The path of the file a cell containing `code` is dumped to by `dumpCell`. Use this as the
+    /// source path of the frames passed to [ExecutionContext::trace](crate::kernel::ExecutionContext::trace),
+    /// so that the frontend can match them to its cells.
+    pub fn source_path(&self, code: &str) -> String {
+        format!(
+            "{}{}{}",
+            self.tmp_file_prefix(),
+            murmur2(code.as_bytes(), HASH_SEED),
+            self.inner.file_suffix,
+        )
+    }
+
+    /// Whether a frontend is debugging; execution is only ever paused while it is
+    pub fn is_started(&self) -> bool {
+        self.inner.state.lock().unwrap().started
+    }
+
+    fn tmp_file_prefix(&self) -> String {
+        format!("{}{MAIN_SEPARATOR}", self.inner.directory.display())
+    }
+
+    /// The thread which executes the requests sent to `subshell_id`, or to the main shell
+    fn thread_id(&self, subshell_id: Option<&str>) -> u64 {
+        let Some(subshell_id) = subshell_id else {
+            return MAIN_THREAD_ID;
+        };
+        let mut subshell_threads = self.inner.subshell_threads.lock().unwrap();
+        let next_thread_id = MAIN_THREAD_ID + 1 + subshell_threads.len() as u64;
+        *subshell_threads.entry(subshell_id.to_owned()).or_insert(next_thread_id)
+    }
+
+    /// Pause if there is a breakpoint on the line of the innermost of `frames`, or if the frontend
+    /// asked to step, until the frontend asks to carry on. Only the thread of the execution which
+    /// sent `parent_header` pauses.
+    pub(crate) async fn trace(&self, parent_header: EmptyObjectOr<Header>, frames: Vec<Frame>) -> Result<()> {
+        let subshell_id = match &parent_header {
+            EmptyObjectOr::Object(header) => header.subshell_id.as_deref(),
+            EmptyObjectOr::EmptyObject {} => None,
+        };
+        let thread_id = self.thread_id(subshell_id);
+        let resumed = {
+            let mut state = self.inner.state.lock().unwrap();
+            let Some(frame) = frames.first().filter(|_| state.started) else {
+                return Ok(());
+            };
+            let reason = if state.stepping.remove(&thread_id) {
+                "step"
+            } else if state.breakpoints.get(&frame.source_path).is_some_and(|lines| lines.contains(&frame.line)) {
+                "breakpoint"
+            } else {
+                return Ok(());
+            };
+            println_debug!("Debugger stopped thread {thread_id} at {}:{} ({reason})", frame.source_path, frame.line);
+            let (resume, resumed) = oneshot::channel();
+            let first_frame_id = state.last_frame_id + 1;
+            state.last_frame_id += frames.len() as u64;
+            state.stopped.insert(thread_id, Stopped { frames, first_frame_id, resume });
+            self.event(parent_header, "stopped", json!({
+                "reason": reason,
+                "threadId": thread_id,
+                "allThreadsStopped": false,
+            }))?;
+            resumed
+        };
+        // Fails if the frontend disconnects instead, which also lets execution carry on
+        let _ = resumed.await;
+        Ok(())
+    }
+
+    /// Forget where the execution of `subshell_id` (or of the main shell) was paused, once it has
+    /// finished or been interrupted
+    pub(crate) fn execution_finished(&self, subshell_id: Option<&str>) {
+        let thread_id = self.thread_id(subshell_id);
+        let mut state = self.inner.state.lock().unwrap();
+        state.stopped.remove(&thread_id);
+        state.stepping.remove(&thread_id);
+    }
+
+    /// Stop debugging and forget every breakpoint, e.g. when the kernel restarts
+    pub(crate) fn clear(&self) {
+        *self.inner.state.lock().unwrap() = Default::default();
+        self.inner.subshell_threads.lock().unwrap().clear();
+    }
+
+    /// Answer a `debug_request`
+    pub(crate) fn handle(&self, request: &DebugRequest, parent_header: EmptyObjectOr<Header>) -> DebugReply {
+        let result = self.run_command(&request.command, &request.arguments, parent_header);
+        if let Err(err) = &result {
+            println_debug!("Debug request {} failed: {err:?}", request.command);
+        }
+        let (success, message, body) = match result {
+            Ok(body) => (true, None, body),
+            Err(err) => (false, Some(format!("{err:#}")), Value::Null),
+        };
+        DebugReply {
+            seq: self.next_seq(),
+            message_type: "response".into(),
+            request_seq: request.seq,
+            success,
+            command: request.command.clone(),
+            message,
+            body,
+        }
+    }
+
+    fn run_command(&self, command: &str, arguments: &Value, parent_header: EmptyObjectOr<Header>) -> Result<Value> {
+        let mut state = self.inner.state.lock().unwrap();
+        Ok(match command {
+            "initialize" => {
+                state.started = true;
+                self.event(parent_header, "initialized", Value::Null)?;
+                json!({
+                    "supportsConfigurationDoneRequest": true,
+                })
+            },
+            "attach" | "configurationDone" => Value::Null,
+            "disconnect" => {
+                // dropping `stopped` lets every paused execution carry on
+                *state = Default::default();
+                Value::Null
+            },
+            "debugInfo" => json!({
+                "isStarted": state.started,
+                "hashMethod": "Murmur2",
+                "hashSeed": HASH_SEED,
+                "tmpFilePrefix": self.tmp_file_prefix(),
+                "tmpFileSuffix": self.inner.file_suffix,
+                "breakpoints": state.breakpoints.iter().map(|(path, lines)| json!({
+                    "source": path,
+                    "breakpoints": lines.iter().map(|line| json!({"line": line})).collect::<Vec<_>>(),
+                })).collect::<Vec<_>>(),
+                "stoppedThreads": state.stopped.keys().collect::<Vec<_>>(),
+                "richRendering": false,
+                "exceptionPaths": [],
+            }),
+            "dumpCell" => {
+                let code = arguments.get("code").and_then(Value::as_str).context("dumpCell needs the code of the cell")?;
+                let path = self.source_path(code);
+                std::fs::create_dir_all(&self.inner.directory)
+                    .with_context(|| format!("Failed to create {}", self.inner.directory.display()))?;
+                std::fs::write(&path, code).with_context(|| format!("Failed to write {path}"))?;
+                json!({"sourcePath": path})
+            },
+            "setBreakpoints" => {
+                let path = arguments
+                    .pointer("/source/path")
+                    .and_then(Value::as_str)
+                    .context("setBreakpoints needs the path of the source")?;
+                let lines: Vec<usize> = arguments
+                    .get("breakpoints")
+                    .and_then(Value::as_array)
+                    .into_iter()
+                    .flatten()
+                    .filter_map(|breakpoint| breakpoint.get("line")?.as_u64())
+                    .map(|line| line as usize)
+                    .collect();
+                let breakpoints = lines
+                    .iter()
+                    .map(|line| json!({"verified": true, "line": line, "source": {"path": path}}))
+                    .collect::<Vec<_>>();
+                if lines.is_empty() {
+                    state.breakpoints.remove(path);
+                } else {
+                    state.breakpoints.insert(path.to_owned(), lines);
+                }
+                json!({"breakpoints": breakpoints})
+            },
+            "threads" => {
+                let mut subshell_threads: Vec<(u64, String)> = self.inner.subshell_threads.lock().unwrap()
+                    .iter()
+                    .map(|(subshell_id, thread_id)| (*thread_id, subshell_id.clone()))
+                    .collect();
+                subshell_threads.sort();
+                let threads = [json!({"id": MAIN_THREAD_ID, "name": "Main Thread"})]
+                    .into_iter()
+                    .chain(subshell_threads.into_iter().map(|(thread_id, subshell_id)| json!({
+                        "id": thread_id,
+                        "name": format!("Subshell {subshell_id}"),
+                    })))
+                    .collect::<Vec<_>>();
+                json!({"threads": threads})
+            },
+            "stackTrace" => {
+                let thread_id = arguments.get("threadId").and_then(Value::as_u64).context("stackTrace needs a threadId")?;
+                let (first_frame_id, frames) = state
+                    .stopped
+                    .get(&thread_id)
+                    .map(|stopped| (stopped.first_frame_id, stopped.frames.as_slice()))
+                    .unwrap_or_default();
+                json!({
+                    "stackFrames": frames.iter().enumerate().map(|(index, frame)| json!({
+                        "id": first_frame_id + index as u64,
+                        "name": frame.name,
+                        "line": frame.line,
+                        "column": 1,
+                        "source": {"path": frame.source_path},
+                    })).collect::<Vec<_>>(),
+                    "totalFrames": frames.len(),
+                })
+            },
+            "scopes" => {
+                let frame_id = arguments.get("frameId").and_then(Value::as_u64).context("scopes needs a frameId")?;
+                // each frame has a single scope, which shares the id of the frame
+                json!({"scopes": [{"name": "Locals", "variablesReference": frame_id, "expensive": false}]})
+            },
+            "variables" => {
+                let reference = arguments
+                    .get("variablesReference")
+                    .and_then(Value::as_u64)
+                    .context("variables needs a variablesReference")?;
+                let frame = state
+                    .stopped
+                    .values()
+                    .find_map(|stopped| stopped.frames.get(reference.checked_sub(stopped.first_frame_id)? as usize))
+                    .with_context(|| format!("There are no variables with reference {reference}"))?;
+                json!({"variables": frame.variables.iter().map(Variable::to_json).collect::<Vec<_>>()})
+            },
+            "inspectVariables" => {
+                // the request does not say which thread it is about, so show the variables of the
+                // main shell if it is paused, or else of the first paused subshell
+                let variables = state
+                    .stopped
+                    .values()
+                    .next()
+                    .and_then(|stopped| stopped.frames.first())
+                    .map(|frame| frame.variables.iter().map(Variable::to_json).collect::<Vec<_>>())
+                    .unwrap_or_default();
+                json!({"variables": variables})
+            },
+            "source" => {
+                let path = arguments
+                    .pointer("/source/path")
+                    .and_then(Value::as_str)
+                    .context("source needs the path of the source")?;
+                let content = std::fs::read_to_string(path).with_context(|| format!("Failed to read {path}"))?;
+                json!({"content": content})
+            },
+            "continue" | "next" | "stepIn" => {
+                let thread_id = arguments
+                    .get("threadId")
+                    .and_then(Value::as_u64)
+                    .with_context(|| format!("{command} needs a threadId"))?;
+                let stopped = state
+                    .stopped
+                    .remove(&thread_id)
+                    .with_context(|| format!("Thread {thread_id} is not paused"))?;
+                if command != "continue" {
+                    state.stepping.insert(thread_id);
+                }
+                let _ = stopped.resume.send(());
+                self.event(parent_header, "continued", json!({"threadId": thread_id, "allThreadsContinued": false}))?;
+                if command == "continue" {
+                    json!({"allThreadsContinued": false})
+                } else {
+                    Value::Null
+                }
+            },
+            command => anyhow::bail!("Unsupported debug command {command:?}"),
+        })
+    }
+
+    fn next_seq(&self) -> u64 {
+        self.inner.seq.fetch_add(1, Ordering::Relaxed)
+    }
+
+    fn event(&self, parent_header: EmptyObjectOr<Header>, event: &str, body: Value) -> Result<()> {
+        self.inner.iopub.publish(
+            parent_header,
+            DebugEventPublication {
+                seq: self.next_seq(),
+                message_type: "event".into(),
+                event: event.into(),
+                body,
+            },
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        protocol::{MessageContent, MessageParsed, MessageType},
+        server::Session,
+    };
+    use tokio::sync::mpsc::UnboundedReceiver;
+
+    fn request(debugger: &Debugger, command: &str, arguments: Value) -> DebugReply {
+        let request = DebugRequest {
+            seq: 1,
+            message_type: "request".into(),
+            command: command.into(),
+            arguments,
+        };
+        debugger.handle(&request, Default::default())
+    }
+
+    fn events(published: &mut UnboundedReceiver<MessageParsed>) -> Vec<String> {
+        std::iter::from_fn(|| published.try_recv().ok())
+            .filter_map(|message| match message.content {
+                MessageContent::DebugEventPublication(event) => Some(event.event),
+                _ => None,
+            })
+            .collect()
+    }
+
+    #[tokio::test]
+    async fn test_pause_at_breakpoint() {
+        let (iopub, mut published) = Iopub::new(Session::new(Default::default()), Default::default());
+        let debugger = Debugger::new(iopub, ".nick");
+        let path = debugger.source_path("a\nb\n");
+        let frame = {
+            let path = path.clone();
+            move |line: usize| vec![Frame {
+                name: "cell".into(),
+                source_path: path.clone(),
+                line,
+                variables: vec![Variable { name: "x".into(), value: line.to_string(), type_name: None }],
+            }]
+        };
+
+        // nothing pauses until the frontend starts debugging
+        debugger.trace(Default::default(), frame(1)).await.unwrap();
+        assert!(request(&debugger, "initialize", json!({})).success);
+        let reply = request(&debugger, "setBreakpoints", json!({"source": {"path": path}, "breakpoints": [{"line": 2}]}));
+        assert_eq!(reply.body["breakpoints"][0]["verified"], true);
+        assert_eq!(request(&debugger, "debugInfo", json!({})).body["breakpoints"][0]["source"], path);
+        debugger.trace(Default::default(), frame(1)).await.unwrap();
+        assert_eq!(events(&mut published), ["initialized"]);
+
+        let paused = tokio::spawn({
+            let debugger = debugger.clone();
+            async move { debugger.trace(Default::default(), frame(2)).await }
+        });
+        while debugger.inner.state.lock().unwrap().stopped.is_empty() {
+            tokio::task::yield_now().await;
+        }
+        assert_eq!(events(&mut published), ["stopped"]);
+        assert_eq!(request(&debugger, "stackTrace", json!({"threadId": 1})).body["stackFrames"][0]["line"], 2);
+        assert_eq!(request(&debugger, "variables", json!({"variablesReference": 1})).body["variables"][0]["value"], "2");
+        assert!(!paused.is_finished());
+
+        assert!(request(&debugger, "continue", json!({"threadId": 1})).success);
+        paused.await.unwrap().unwrap();
+        assert!(!request(&debugger, "continue", json!({"threadId": 1})).success);
+    }
+
+    #[tokio::test]
+    async fn test_subshells_pause_independently() {
+        let (iopub, _published) = Iopub::new(Session::new(Default::default()), Default::default());
+        let session = Session::new(Default::default());
+        let debugger = Debugger::new(iopub, ".nick");
+        let path = debugger.source_path("a\n");
+        assert!(request(&debugger, "initialize", json!({})).success);
+        request(&debugger, "setBreakpoints", json!({"source": {"path": path}, "breakpoints": [{"line": 1}]}));
+
+        let pause = |subshell_id: Option<&str>, name: &str| {
+            let mut header = session.header(MessageType::ExecuteRequest);
+            header.subshell_id = subshell_id.map(str::to_owned);
+            let frames = vec![Frame { name: name.into(), source_path: path.clone(), line: 1, variables: vec![] }];
+            tokio::spawn({
+                let debugger = debugger.clone();
+                async move { debugger.trace(header.into(), frames).await }
+            })
+        };
+        let main = pause(None, "main");
+        let subshell = pause(Some("s"), "subshell");
+        while debugger.inner.state.lock().unwrap().stopped.len() < 2 {
+            tokio::task::yield_now().await;
+        }
+        assert_eq!(request(&debugger, "debugInfo", json!({})).body["stoppedThreads"], json!([1, 2]));
+        assert_eq!(request(&debugger, "threads", json!({})).body["threads"][1]["name"], "Subshell s");
+        let main_frame = &request(&debugger, "stackTrace", json!({"threadId": 1})).body["stackFrames"][0];
+        let subshell_frame = &request(&debugger, "stackTrace", json!({"threadId": 2})).body["stackFrames"][0];
+        assert_eq!((&main_frame["name"], &subshell_frame["name"]), (&json!("main"), &json!("subshell")));
+        assert_ne!(main_frame["id"], subshell_frame["id"]);
+
+        // resuming one thread leaves the other paused
+        assert!(request(&debugger, "continue", json!({"threadId": 2})).success);
+        subshell.await.unwrap().unwrap();
+        assert!(!main.is_finished());
+        assert_eq!(request(&debugger, "debugInfo", json!({})).body["stoppedThreads"], json!([1]));
+        assert!(request(&debugger, "continue", json!({"threadId": 1})).success);
+        main.await.unwrap().unwrap();
+    }
+}
@@ -0,0 +1,43 @@
+/// The 32 bit MurmurHash2 of `data`.
+///
+/// JupyterLab uses this to work out the path of the file a cell was dumped to (see
+/// [Debugger::source_path](super::Debugger::source_path)), so it must match `murmur2` in
+/// `@jupyterlab/debugger` exactly.
+pub fn murmur2(data: &[u8], seed: u32) -> u32 {
+    const M: u32 = 0x5bd1e995;
+    const R: u32 = 24;
+
+    let mut h = seed ^ data.len() as u32;
+    let mut chunks = data.chunks_exact(4);
+    for chunk in &mut chunks {
+        let mut k = u32::from_le_bytes([chunk[0], chunk[1], chunk[2], chunk[3]]);
+        k = k.wrapping_mul(M);
+        k ^= k >> R;
+        k = k.wrapping_mul(M);
+        h = h.wrapping_mul(M) ^ k;
+    }
+    let rest = chunks.remainder();
+    if !rest.is_empty() {
+        for (index, byte) in rest.iter().enumerate().rev() {
+            h ^= (*byte as u32) << (8 * index);
+        }
+        h = h.wrapping_mul(M);
+    }
+    h ^= h >> 13;
+    h = h.wrapping_mul(M);
+    h ^= h >> 15;
+    h
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_murmur2() {
+        assert_eq!(murmur2(b"", 0), 0);
+        assert_eq!(murmur2(b"a", 0), 2456313694);
+        assert_eq!(murmur2(b"The quick brown fox jumps over the lazy dog", 0x9747b28c), 0x1d84d036);
+        assert_eq!(murmur2(b"print(\"hi\")\n", 3339675911), 756419970);
+    }
+}
@@ -0,0 +1,93 @@
+/// Match `text` against a shell style glob `pattern`, as used by the `pattern` of a `search`
+/// history request.
+///
+/// Supports `*` (any run of characters), `?` (any single character) and `[...]` character
+/// classes, which may contain ranges like `a-z` and may be negated with a leading `!` or `^`.
+/// Matching is case sensitive and applies to the whole of `text`.
+pub fn glob_match(pattern: &str, text: &str) -> bool {
+    let pattern: Vec<char> = pattern.chars().collect();
+    let text: Vec<char> = text.chars().collect();
+
+    // Iterative matching with backtracking to the most recent `*`
+    let (mut p, mut t) = (0, 0);
+    let mut backtrack: Option<(usize, usize)> = None;
+    while t < text.len() {
+        let step = match pattern.get(p) {
+            Some('*') => {
+                backtrack = Some((p, t));
+                p += 1;
+                continue;
+            },
+            Some('?') => Some(1),
+            Some('[') => match_class(&pattern[p..], text[t]),
+            Some(c) if *c == text[t] => Some(1),
+            _ => None,
+        };
+        match (step, backtrack) {
+            (Some(width), _) => {
+                p += width;
+                t += 1;
+            },
+            (None, Some((star, start))) => {
+                // let the last `*` swallow one more character and try again
+                p = star + 1;
+                t = start + 1;
+                backtrack = Some((star, start + 1));
+            },
+            (None, None) => return false,
+        }
+    }
+    pattern[p..].iter().all(|c| *c == '*')
+}
+
+/// Try to match `c` against the character class at the start of `pattern`. Returns the length of
+/// the class in the pattern if it matched. A `[` with no closing `]` is treated as a literal.
+fn match_class(pattern: &[char], c: char) -> Option<usize> {
+    let mut i = 1;
+    let negated = matches!(pattern.get(i), Some('!' | '^'));
+    if negated {
+        i += 1;
+    }
+    let mut matched = false;
+    let mut first = true;
+    loop {
+        match pattern.get(i) {
+            None => return (c == '[').then_some(1),
+            Some(']') if !first => break,
+            Some(&low) => {
+                if pattern.get(i + 1) == Some(&'-') && pattern.get(i + 2).is_some_and(|high| *high != ']') {
+                    matched |= low <= c && c <= pattern[i + 2];
+                    i += 3;
+                } else {
+                    matched |= low == c;
+                    i += 1;
+                }
+            },
+        }
+        first = false;
+    }
+    (matched != negated).then_some(i + 1)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_glob_match() {
+        assert!(glob_match("*", ""));
+        assert!(glob_match("print*", "print(1)"));
+        assert!(glob_match("*(1)", "print(1)"));
+        assert!(glob_match("p?int*", "print(1)"));
+        assert!(glob_match("*a*b*", "xxaxxbxx"));
+        assert!(!glob_match("*a*b", "xxaxxbxx"));
+        assert!(!glob_match("print", "print(1)"));
+        assert!(!glob_match("Print*", "print(1)"));
+        assert!(glob_match("[a-c]x", "bx"));
+        assert!(!glob_match("[!a-c]x", "bx"));
+        assert!(glob_match("[^a-c]x", "dx"));
+        assert!(glob_match("[]]", "]"));
+        assert!(glob_match("[a-]", "-"));
+        assert!(glob_match("a[", "a["));
+    }
+}
@@ -0,0 +1,282 @@
+//! A record of the cells which have been executed, used to answer `history_request`s.
+//!
+//! Each cell is identified by the session it was run in and its line number within that session.
+//! A new session starts each time the kernel starts (or restarts), and line numbers start again
+//! at 1 in every session.
+//!
+//! By default the history only lasts as long as the kernel process. Use [History::open] to also
+//! save it to a [HistoryStore], so that the cells of earlier sessions are available to later ones.
+
+mod glob;
+mod store;
+
+pub use glob::glob_match;
+pub use store::HistoryStore;
+
+use crate::protocol::{
+    HistoryAccessType,
+    HistoryItem,
+    HistoryReply,
+    HistoryRequest,
+    ReplyStatus,
+};
+
+use anyhow::Result;
+use serde::{Deserialize, Serialize};
+use std::{collections::HashSet, path::PathBuf};
+
+/// The number of cells returned by `tail` requests that do not specify `n`; `search` requests
+/// which do not specify it return every match
+const DEFAULT_NUMBER_OF_ITEMS: usize = 10;
+
+/// A single executed cell
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct HistoryEntry {
+    pub session: u32,
+    pub line: usize,
+    pub input: String,
+    /// The `text/plain` representation of the result, if the cell produced one
+    pub output: Option<String>,
+}
+
+/// The history of executed cells, kept in memory and optionally saved to disk
+#[derive(Debug, Clone)]
+pub struct History {
+    session: u32,
+    entries: Vec<HistoryEntry>,
+    store: Option<HistoryStore>,
+}
+
+impl Default for History {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl History {
+    /// A history which is only kept in memory, starting at session 1
+    pub fn new() -> Self {
+        Self {
+            session: 1,
+            entries: Vec::new(),
+            store: None,
+        }
+    }
+
+    /// A history saved to the file at `path`. Cells from earlier sessions are loaded from the
+    /// file, and the current session is numbered one after the latest session in it.
+    pub fn open(path: impl Into<PathBuf>) -> Result<Self> {
+        let store = HistoryStore::new(path);
+        let (session, entries) = store.start_session()?;
+        Ok(Self {
+            session,
+            entries,
+            store: Some(store),
+        })
+    }
+
+    /// The number of the current session
+    pub fn session(&self) -> u32 {
+        self.session
+    }
+
+    /// Start a new session, e.g. when the kernel is restarted. Earlier sessions remain available.
+    ///
+    /// If the history is saved to disk, this also picks up any cells saved by other kernels in the
+    /// meantime.
+    pub fn new_session(&mut self) -> Result<()> {
+        match &self.store {
+            Some(store) => (self.session, self.entries) = store.start_session()?,
+            None => self.session += 1,
+        }
+        Ok(())
+    }
+
+    /// Every cell recorded so far, oldest first
+    pub fn entries(&self) -> &[HistoryEntry] {
+        &self.entries
+    }
+
+    /// Record a cell in the current session, returning its line number.
+    ///
+    /// The cell is kept in memory even if saving it to disk fails.
+    pub fn append(&mut self, input: &str, output: Option<String>) -> Result<usize> {
+        let line = self.current_session_entries().last().map_or(1, |entry| entry.line + 1);
+        let entry = HistoryEntry {
+            session: self.session,
+            line,
+            input: input.to_owned(),
+            output,
+        };
+        self.entries.push(entry);
+        if let Some(store) = &self.store {
+            store.append(&self.entries[self.entries.len() - 1])?;
+        }
+        Ok(line)
+    }
+
+    fn current_session_entries(&self) -> impl DoubleEndedIterator<Item = &HistoryEntry> {
+        self.entries.iter().filter(move |entry| entry.session == self.session)
+    }
+
+    /// The last `n` cells, across all sessions
+    pub fn tail(&self, n: usize) -> Vec<&HistoryEntry> {
+        self.entries[self.entries.len().saturating_sub(n)..].iter().collect()
+    }
+
+    /// The cells of `session` with line numbers from `start` up to, but not including, `stop`.
+    ///
+    /// A `session` of zero or less counts back from the current session, so `0` is the current
+    /// session and `-1` the one before it. `stop` of `None` means the end of the session.
+    pub fn range(&self, session: i32, start: i32, stop: Option<i32>) -> Vec<&HistoryEntry> {
+        let session = if session <= 0 {
+            self.session as i64 + session as i64
+        } else {
+            session as i64
+        };
+        self.entries
+            .iter()
+            .filter(|entry| entry.session as i64 == session)
+            .filter(|entry| entry.line as i64 >= start as i64)
+            .filter(|entry| stop.is_none_or(|stop| (entry.line as i64) < stop as i64))
+            .collect()
+    }
+
+    /// The last `n` cells whose input matches the glob `pattern`, or all of them if `n` is
+    /// `None`. If `unique` is set, only the most recent of cells with identical input is kept.
+    pub fn search(&self, pattern: &str, n: Option<usize>, unique: bool) -> Vec<&HistoryEntry> {
+        let mut seen = HashSet::new();
+        let mut found: Vec<&HistoryEntry> = self.entries
+            .iter()
+            .rev()
+            .filter(|entry| glob_match(pattern, &entry.input))
+            .filter(|entry| !unique || seen.insert(entry.input.as_str()))
+            .take(n.unwrap_or(usize::MAX))
+            .collect();
+        found.reverse();
+        found
+    }
+
+    /// Answer a `history_request`
+    pub fn reply(&self, request: &HistoryRequest) -> HistoryReply {
+        let entries = match request.history_access_type {
+            HistoryAccessType::Tail => self.tail(
+                request.number_of_items.unwrap_or(DEFAULT_NUMBER_OF_ITEMS),
+            ),
+            HistoryAccessType::Range => self.range(
+                request.kernel_session.unwrap_or(0),
+                request.line_start.unwrap_or(1),
+                request.line_stop,
+            ),
+            HistoryAccessType::Search => self.search(
+                request.search_pattern.as_deref().unwrap_or("*"),
+                request.number_of_items,
+                request.unique.unwrap_or(false),
+            ),
+        };
+        HistoryReply {
+            status: ReplyStatus::Ok,
+            history: entries
+                .into_iter()
+                .map(|entry| if request.output {
+                    HistoryItem::InputOutput(entry.session, entry.line, (entry.input.clone(), entry.output.clone()))
+                } else {
+                    HistoryItem::Input(entry.session, entry.line, entry.input.clone())
+                })
+                .collect(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn lines(entries: Vec<&HistoryEntry>) -> Vec<(u32, usize)> {
+        entries.into_iter().map(|entry| (entry.session, entry.line)).collect()
+    }
+
+    fn example() -> History {
+        let mut history = History::new();
+        history.append("a = 1", None).unwrap();
+        history.append("print(a)", Some("1".into())).unwrap();
+        history.new_session().unwrap();
+        history.append("b = 2", None).unwrap();
+        history.append("print(a)", Some("error".into())).unwrap();
+        history.append("print(b)", Some("2".into())).unwrap();
+        history
+    }
+
+    #[test]
+    fn test_tail() {
+        let history = example();
+        assert_eq!(lines(history.tail(2)), vec![(2, 2), (2, 3)]);
+        assert_eq!(lines(history.tail(4)), vec![(1, 2), (2, 1), (2, 2), (2, 3)]);
+        assert_eq!(history.tail(100).len(), 5);
+    }
+
+    #[test]
+    fn test_range() {
+        let history = example();
+        assert_eq!(lines(history.range(0, 1, None)), vec![(2, 1), (2, 2), (2, 3)]);
+        assert_eq!(lines(history.range(0, 2, Some(3))), vec![(2, 2)]);
+        assert_eq!(lines(history.range(-1, 1, None)), vec![(1, 1), (1, 2)]);
+        assert_eq!(lines(history.range(1, 2, None)), vec![(1, 2)]);
+        assert_eq!(lines(history.range(-5, 1, None)), vec![]);
+    }
+
+    #[test]
+    fn test_search() {
+        let history = example();
+        assert_eq!(lines(history.search("print*", None, false)), vec![(1, 2), (2, 2), (2, 3)]);
+        assert_eq!(lines(history.search("print*", Some(1), false)), vec![(2, 3)]);
+        assert_eq!(lines(history.search("print*", None, true)), vec![(2, 2), (2, 3)]);
+        assert_eq!(lines(history.search("? = ?", None, false)), vec![(1, 1), (2, 1)]);
+    }
+
+    #[test]
+    fn test_reply_search_without_n() {
+        let mut history = History::new();
+        for line in 0..DEFAULT_NUMBER_OF_ITEMS + 5 {
+            history.append(&format!("print({line})"), None).unwrap();
+        }
+        let request: HistoryRequest = serde_json::from_str(
+            r#"{"output":false,"raw":true,"hist_access_type":"search","pattern":"print*"}"#
+        ).unwrap();
+        assert_eq!(history.reply(&request).history.len(), DEFAULT_NUMBER_OF_ITEMS + 5);
+        let request: HistoryRequest = serde_json::from_str(
+            r#"{"output":false,"raw":true,"hist_access_type":"tail"}"#
+        ).unwrap();
+        assert_eq!(history.reply(&request).history.len(), DEFAULT_NUMBER_OF_ITEMS);
+    }
+
+    #[test]
+    fn test_reply_with_output() {
+        let history = example();
+        let request: HistoryRequest = serde_json::from_str(
+            r#"{"output":true,"raw":true,"hist_access_type":"range","session":-1,"start":2}"#
+        ).unwrap();
+        assert_eq!(history.reply(&request).history, vec![
+            HistoryItem::InputOutput(1, 2, ("print(a)".into(), Some("1".into()))),
+        ]);
+    }
+
+    #[test]
+    fn test_open_continues_sessions() {
+        let path = std::env::temp_dir().join(format!("nikkerish-history-{}.jsonl", uuid::Uuid::new_v4()));
+
+        let mut first = History::open(&path).unwrap();
+        first.append("a = 1", None).unwrap();
+        // a second kernel sharing the file while the first is still running
+        let mut second = History::open(&path).unwrap();
+        second.append("b = 2", None).unwrap();
+        first.append("print(a)", Some("1".into())).unwrap();
+
+        let third = History::open(&path).unwrap();
+        std::fs::remove_file(&path).unwrap();
+
+        assert_eq!((first.session(), second.session(), third.session()), (1, 2, 3));
+        assert_eq!(lines(third.tail(10)), vec![(1, 1), (2, 1), (1, 2)]);
+        assert_eq!(lines(third.range(-2, 1, None)), vec![(1, 1), (1, 2)]);
+    }
+}
@@ -0,0 +1,91 @@
+use super::HistoryEntry;
+use crate::util::iso_8601_Z_now;
+
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+use std::{
+    fs::{self, File, OpenOptions},
+    io::{BufRead, BufReader, Write},
+    path::{Path, PathBuf},
+};
+use tracing::debug;
+
+/// One line of the history file
+#[derive(Serialize, Deserialize, Debug)]
+#[serde(tag = "type", rename_all = "snake_case")]
+enum Record {
+    /// Written when a kernel starts, to claim a session number
+    Session { session: u32, started: String },
+    /// Written after each cell is executed
+    Cell(HistoryEntry),
+}
+
+/// History saved to disk as a file of json lines, so that it survives the kernel process.
+///
+/// Several kernels may share the same file. Every read or write takes an exclusive lock on the
+/// whole file, and every record is appended as a single line, so concurrent kernels never see or
+/// write half a record and never claim the same session number.
+#[derive(Debug, Clone)]
+pub struct HistoryStore {
+    path: PathBuf,
+}
+
+impl HistoryStore {
+    pub fn new(path: impl Into<PathBuf>) -> Self {
+        Self { path: path.into() }
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    fn open_locked(&self) -> Result<File> {
+        if let Some(parent) = self.path.parent() {
+            fs::create_dir_all(parent)
+                .with_context(|| format!("Failed to create history folder {parent:?}"))?;
+        }
+        let file = OpenOptions::new()
+            .read(true)
+            .append(true)
+            .create(true)
+            .open(&self.path)
+            .with_context(|| format!("Failed to open history file {:?}", self.path))?;
+        file.lock()
+            .with_context(|| format!("Failed to lock history file {:?}", self.path))?;
+        Ok(file)
+    }
+
+    fn write_record(mut file: &File, record: &Record) -> Result<()> {
+        let mut line = serde_json::to_string(record)?;
+        line.push('\n');
+        file.write_all(line.as_bytes())
+            .context("Failed to write to history file")
+    }
+
+    /// Claim the next session number, returning it along with every cell saved so far
+    pub fn start_session(&self) -> Result<(u32, Vec<HistoryEntry>)> {
+        let file = self.open_locked()?;
+        let mut last_session = 0;
+        let mut entries = Vec::new();
+        for line in BufReader::new(&file).lines() {
+            let line = line.context("Failed to read history file")?;
+            match serde_json::from_str(&line) {
+                Ok(Record::Session { session, .. }) => last_session = last_session.max(session),
+                Ok(Record::Cell(entry)) => {
+                    last_session = last_session.max(entry.session);
+                    entries.push(entry);
+                },
+                Err(err) => println_debug!("Skipping unreadable line in history file: {err}"),
+            }
+        }
+        let session = last_session + 1;
+        Self::write_record(&file, &Record::Session { session, started: iso_8601_Z_now() })?;
+        Ok((session, entries))
+    }
+
+    /// Save a cell at the end of the file
+    pub fn append(&self, entry: &HistoryEntry) -> Result<()> {
+        let file = self.open_locked()?;
+        Self::write_record(&file, &Record::Cell(entry.clone()))
+    }
+}
@@ -5,11 +5,12 @@ use std::{
 };
 use serde::Serialize;
 use anyhow::{Context, Result};
+use clap::ValueEnum;
 
 /// The way in which the client should try to to interrupt cell execution on this kernel,
-#[derive(Debug, Serialize, Default)]
+#[derive(Debug, Serialize, Default, Clone, Copy, ValueEnum)]
 #[serde(rename_all="snake_case")]
-enum InterruptMode{
+pub enum InterruptMode{
     /// Use the operating system’s signalling facilities (e.g. SIGINT on POSIX systems)
     #[default]
     Signal,
@@ -50,7 +51,7 @@ struct KernelSpec{
 /// 
 /// TODO: We can try manually install this according to the standard paths specified here
 ///       https://jupyter-client.readthedocs.io/en/latest/kernels.html#kernel-specs
-pub fn kernel_spec() -> Result<()> {
+pub fn kernel_spec(interrupt_mode: InterruptMode) -> Result<()> {
     let current_executable_path = std::env::current_exe()
         .context("Failed to get current executable path")?;
     let current_executable_path_str = current_executable_path.to_str()
@@ -67,9 +68,7 @@ pub fn kernel_spec() -> Result<()> {
         display_name: "Nickkerish".to_owned(),
         language: "nickkerish".to_owned(),
 
-        // Lets try message, since vscode just flat out does not seem to play nice and I am just
-        // trying random stuff now
-        interrupt_mode: Some(InterruptMode::Message)
+        interrupt_mode: Some(interrupt_mode)
     };
 
     let kernel_folder = PathBuf::from("Nickkerish");
@@ -0,0 +1,228 @@
+use crate::{
+    comm::Comms,
+    debugger::{Debugger, Frame},
+    protocol::{
+        ClearOutputPublication,
+        DisplayDataPublication,
+        ErrorDetails,
+        ExecuteResultPublication,
+        Header,
+        InputRequest,
+        Payload,
+        Publication,
+        StreamPublication,
+        Transient,
+        UpdateDisplayDataPublication,
+    },
+    server::{Iopub, Stdin},
+    util::EmptyObjectOr,
+};
+
+use anyhow::Result;
+use bytes::Bytes;
+use std::sync::Mutex;
+
+/// Handed to [Kernel::execute](super::Kernel::execute) so that the kernel can publish the
+/// side effects of an execution to all connected clients.
+///
+/// Everything published through the context is sent on iopub with the `execute_request` as
+/// its parent header, so that clients can tell which cell the output belongs to. Nothing is
+/// published for a `silent` request; the spec says it will "*not* broadcast output on the IOPUB
+/// channel".
+#[derive(Debug)]
+pub struct ExecutionContext {
+    iopub: Iopub,
+    /// `None` unless the `execute_request` had `allow_stdin` set
+    stdin: Option<Stdin>,
+    comms: Comms,
+    debugger: Debugger,
+    /// The identities of the client which sent the `execute_request`
+    identities: Vec<Bytes>,
+    parent_header: EmptyObjectOr<Header>,
+    execution_count: usize,
+    /// Set if the `execute_request` was `silent`, in which case outputs are discarded
+    silent: bool,
+    /// The `text/plain` representation of the last result, kept for the history
+    output: Mutex<Option<String>>,
+    /// Sent back in the execute_reply
+    payloads: Mutex<Vec<Payload>>,
+}
+
+impl ExecutionContext {
+    #[allow(clippy::too_many_arguments)]
+    pub(crate) fn new(
+        iopub: Iopub,
+        stdin: Option<Stdin>,
+        comms: Comms,
+        debugger: Debugger,
+        identities: Vec<Bytes>,
+        parent_header: EmptyObjectOr<Header>,
+        execution_count: usize,
+        silent: bool,
+    ) -> Self {
+        Self {
+            iopub,
+            stdin,
+            comms,
+            debugger,
+            identities,
+            parent_header,
+            execution_count,
+            silent,
+            output: Mutex::new(None),
+            payloads: Mutex::new(Vec::new()),
+        }
+    }
+
+    /// The execution count of the cell being executed
+    pub fn execution_count(&self) -> usize {
+        self.execution_count
+    }
+
+    /// The registry of comms, used to open comms to the frontend
+    pub fn comms(&self) -> &Comms {
+        &self.comms
+    }
+
+    /// The debugger, used to name the source of the cell; see [Debugger::source_path]
+    pub fn debugger(&self) -> &Debugger {
+        &self.debugger
+    }
+
+    /// Call before running each line of code, with the call stack at that line. If the frontend
+    /// is debugging and there is a breakpoint on the line, or the frontend is stepping through the
+    /// code, this waits until the frontend asks to carry on.
+    pub async fn trace(&self, frames: Vec<Frame>) -> Result<()> {
+        self.debugger.trace(self.parent_header.clone(), frames).await
+    }
+
+    /// Write `text` to the named stream; either `"stdout"` or `"stderr"`
+    pub fn stream(&self, name: &str, text: &str) -> Result<()> {
+        self.publish(
+            StreamPublication {
+                name: name.into(),
+                text: text.into(),
+            },
+        )
+    }
+
+    /// Publish the result of the execution.
+    ///
+    /// `data` is a mime bundle; an object mapping mime-types to the representation of the result
+    /// in that format. It should always contain a `"text/plain"` representation.
+    pub fn execute_result(&self, data: serde_json::Value) -> Result<()> {
+        if let Some(text) = data.get("text/plain").and_then(|text| text.as_str()) {
+            *self.output.lock().unwrap() = Some(text.to_owned());
+        }
+        self.publish(
+            ExecuteResultPublication {
+                execution_count: self.execution_count,
+                data,
+                metadata: Default::default(),
+            },
+        )
+    }
+
+    /// Display `data`, a mime bundle like in [ExecutionContext::execute_result], as a separate
+    /// output. A cell can display any number of outputs.
+    ///
+    /// Give a `display_id` to be able to replace the output later using
+    /// [ExecutionContext::update_display_data].
+    pub fn display_data(&self, data: serde_json::Value, display_id: Option<&str>) -> Result<()> {
+        self.display_data_with_buffers(data, display_id, Vec::new())
+    }
+
+    /// Like [ExecutionContext::display_data], with binary buffers attached to the message, e.g.
+    /// the raw pixels of an image for a frontend extension which understands them. Buffers spare
+    /// large binary outputs the cost of base64 encoding them into `data`.
+    pub fn display_data_with_buffers(
+        &self,
+        data: serde_json::Value,
+        display_id: Option<&str>,
+        buffers: Vec<Bytes>,
+    ) -> Result<()> {
+        self.publish_with_buffers(
+            DisplayDataPublication {
+                data,
+                metadata: Default::default(),
+                transient: Transient {
+                    display_id: display_id.map(Into::into),
+                },
+            },
+            buffers,
+        )
+    }
+
+    /// Replace the content of every output previously displayed with `display_id`, in place. The
+    /// display may have been created by an earlier cell.
+    pub fn update_display_data(&self, data: serde_json::Value, display_id: &str) -> Result<()> {
+        self.publish(
+            UpdateDisplayDataPublication {
+                data,
+                metadata: Default::default(),
+                transient: Transient {
+                    display_id: Some(display_id.into()),
+                },
+            },
+        )
+    }
+
+    /// Clear the outputs of the cell being executed.
+    ///
+    /// If `wait` is set, the frontend keeps showing the old output until the next output arrives,
+    /// then replaces it; redrawing this way avoids flicker in animations and progress printouts.
+    pub fn clear_output(&self, wait: bool) -> Result<()> {
+        self.publish(ClearOutputPublication { wait })
+    }
+
+    /// Ask the user to enter a line of text, showing `prompt`. If `password` is set, the frontend
+    /// should not echo what is typed.
+    ///
+    /// Waits until the frontend answers. Fails with a `StdinNotImplementedError` if the frontend
+    /// did not set `allow_stdin` on the `execute_request`.
+    pub async fn input(&self, prompt: &str, password: bool) -> Result<String> {
+        let Some(stdin) = &self.stdin else {
+            return Err(ErrorDetails::new(
+                "StdinNotImplementedError",
+                "input was called, but this frontend does not support input requests.",
+            ).into());
+        };
+        stdin.input(
+            &self.identities,
+            self.parent_header.clone(),
+            InputRequest {
+                prompt: prompt.into(),
+                password,
+            },
+        ).await
+    }
+
+    /// Ask the frontend to act once the execution has finished, e.g. to fill in the next cell with
+    /// [Payload::SetNextInput]. Payloads are sent in the execute_reply, even if the request was
+    /// `silent`, but are dropped if the execution fails.
+    pub fn payload(&self, payload: Payload) {
+        self.payloads.lock().unwrap().push(payload);
+    }
+
+    /// Publish on iopub with the `execute_request` as parent, unless it was `silent`
+    fn publish(&self, content: impl Publication) -> Result<()> {
+        self.publish_with_buffers(content, Vec::new())
+    }
+
+    fn publish_with_buffers(&self, content: impl Publication, buffers: Vec<Bytes>) -> Result<()> {
+        if self.silent {
+            return Ok(());
+        }
+        self.iopub.publish_with_buffers(self.parent_header.clone(), content, Default::default(), buffers)
+    }
+
+    /// The `text/plain` representation of the result published by this execution, if any
+    pub(crate) fn output(&self) -> Option<String> {
+        self.output.lock().unwrap().clone()
+    }
+
+    /// The payloads added by this execution, see [ExecutionContext::payload]
+    pub(crate) fn take_payloads(&self) -> Vec<Payload> {
+        std::mem::take(&mut self.payloads.lock().unwrap())
+    }
+}
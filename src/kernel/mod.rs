@@ -0,0 +1,112 @@
+mod execution_context;
+
+pub use execution_context::ExecutionContext;
+
+use crate::{
+    comm::Comms,
+    history::History,
+    protocol::{
+        CompleteReply,
+        CompleteRequest,
+        ErrorDetails,
+        ExecuteRequest,
+        HistoryReply,
+        HistoryRequest,
+        InspectReply,
+        InspectRequest,
+        IsCompleteReply,
+        KernelInfoReply,
+    },
+};
+
+use anyhow::Result;
+use std::future::Future;
+
+/// The language specific behaviour of a Jupyter kernel.
+///
+/// The [server](crate::server::serve) takes care of the sockets, message signing, status
+/// publications and routing of replies. An implementation of this trait only has to decide what
+/// to do with the content of each request.
+///
+/// Methods take `&self` since the server may need to call into the kernel from more than one task;
+/// implementations should use interior mutability for any state they keep between requests.
+///
+/// Only [Kernel::kernel_info] and [Kernel::execute] are required. The remaining methods have
+/// defaults which give a minimal but valid response as described in
+/// [Compatibility](https://jupyter-client.readthedocs.io/en/latest/messaging.html#compatibility)
+pub trait Kernel: Send + Sync + 'static {
+    /// Respond to a `kernel_info_request` with details about the kernel and the language it
+    /// implements
+    fn kernel_info(&self) -> KernelInfoReply;
+
+    /// Execute the code in an `execute_request`.
+    ///
+    /// Any output should be published using the [ExecutionContext]. The server sends the
+    /// `execute_reply` once the returned future completes.
+    ///
+    /// If the execution fails, the error is published on iopub and sent in an `execute_reply` with
+    /// status `error`. Return an [ErrorDetails](crate::protocol::ErrorDetails) (see
+    /// [ErrorDetails::new](crate::protocol::ErrorDetails::new)) to choose the name and traceback
+    /// shown to the user; any other error is reported as a generic `Error`.
+    ///
+    /// If the execution is interrupted, the returned future is dropped at whichever `.await` it is
+    /// suspended at, and the server replies with a `KeyboardInterrupt` error.
+    fn execute(
+        &self,
+        request: &ExecuteRequest,
+        context: &ExecutionContext,
+    ) -> impl Future<Output = Result<()>> + Send;
+
+    /// Evaluate one of the `user_expressions` of an `execute_request` in the namespace of the
+    /// kernel, once the code has been executed. Return the value of the expression as a mime
+    /// bundle, like [ExecutionContext::execute_result]; it is sent back in the `execute_reply`
+    /// rather than published.
+    ///
+    /// Each expression is evaluated separately, and an error only affects the result of that
+    /// expression. The default fails, since there is no namespace to evaluate expressions in.
+    fn evaluate_user_expression(&self, _expression: &str) -> Result<serde_json::Value> {
+        Err(ErrorDetails::new(
+            "NotImplementedError",
+            "This kernel does not evaluate user_expressions",
+        ).into())
+    }
+
+    /// Respond to an `is_complete_request`; used by console frontends to decide if pressing enter
+    /// should execute the code or start a new line.
+    fn is_complete(&self, _code: &str) -> IsCompleteReply {
+        IsCompleteReply {
+            status: Default::default(),
+            indent: None,
+        }
+    }
+
+    /// Respond to a `complete_request` with possible completions of the code at the cursor
+    fn complete(&self, request: &CompleteRequest) -> CompleteReply {
+        CompleteReply::empty(request.cursor_pos)
+    }
+
+    /// Respond to an `inspect_request` with information about the code at the cursor
+    fn inspect(&self, _request: &InspectRequest) -> InspectReply {
+        InspectReply::default()
+    }
+
+    /// Respond to a `history_request`. `history` holds every cell executed with `store_history`
+    /// set; the default answers the request from it directly.
+    fn history(&self, request: &HistoryRequest, history: &History) -> HistoryReply {
+        history.reply(request)
+    }
+
+    /// Called once when the server starts, to [register](Comms::register_target) a handler for
+    /// each comm target the kernel supports. Comms the frontend opens for any other target are
+    /// closed again straight away.
+    fn register_comm_targets(&self, _comms: &Comms) {}
+
+    /// Called before the kernel shuts down. If `restart` is true the kernel should reset its
+    /// state, ready to be used as if it had just started.
+    fn shutdown(&self, _restart: bool) {}
+
+    /// Called when the client asks the kernel to interrupt the current execution. The server will
+    /// also cancel the future returned by [Kernel::execute], so this only needs to be implemented
+    /// if the kernel has work in progress that the future does not own (e.g. a separate thread).
+    fn interrupt(&self) {}
+}
@@ -0,0 +1,18 @@
+//! A library for implementing Jupyter kernels in Rust.
+//!
+//! Implement the [Kernel](kernel::Kernel) trait for your language, then pass it to
+//! [serve](server::serve) along with the [ConnectionInformation](connection_information::ConnectionInformation)
+//! read from the connection file provided by the jupyter client.
+
+#[macro_use]
+pub mod logging;
+
+pub mod comm;
+pub mod connection_information;
+pub mod debugger;
+pub mod history;
+pub mod kernel;
+pub mod protocol;
+pub mod server;
+pub mod util;
+pub mod widgets;
@@ -28,6 +28,8 @@ pub fn setup() -> Result<tracing_appender::non_blocking::WorkerGuard> {
     Ok(guard)
 }
 
+/// Log at debug level. Callers must have `tracing::debug` in scope.
+#[macro_export]
 macro_rules! println_debug {
     ($($arg:tt)*) => {{
         //println!($($arg)*);
@@ -1,16 +1,10 @@
-#[macro_use]
-mod logging;
-
 mod command_line_interface;
-mod connection_information;
 mod install;
-mod server;
-mod util;
-mod protocol;
-
+mod nickkerish;
 
 use command_line_interface::CommandLineInterface;
-use server::serve;
+use nickkerish::Nickkerish;
+use nikkerish::{history::History, logging, println_debug, server::serve, util::jupyter_data_dir};
 
 use anyhow::Result;
 use clap::Parser;
@@ -21,19 +15,22 @@ async fn main() -> Result<()> {
     let _logging_worker_guard = logging::setup()?;
     println_debug!("Logging setup complete");
     match CommandLineInterface::parse() {
-        CommandLineInterface::InstallKernelSpec => {
+        CommandLineInterface::InstallKernelSpec { interrupt_mode } => {
             println_debug!("Installing Nickkerish Kernel...");
-            install::kernel_spec()
+            install::kernel_spec(interrupt_mode)
                 .inspect_err(|err| println_debug!("Failed to install kernelspec {err}"))?;
             println_debug!("Kernel installed successfully");
         }
         CommandLineInterface::Run {
             mut connection_file,
+            no_history,
+            iopub_topics,
         } => {
             println_debug!("Starting the Nickkerish Kernel...");
             let connection_information = serde_json::from_reader(&mut connection_file)
                 .inspect_err(|err| println_debug!("Failed to read connection file: {err}"))?;
-            serve(connection_information)
+            let history = if no_history { History::new() } else { open_history() };
+            serve(connection_information, Nickkerish::default(), history, iopub_topics)
                 .await
                 .inspect_err(|err| println_debug!("Server Failed: {err}"))?;
         }
@@ -41,3 +38,15 @@ async fn main() -> Result<()> {
     println_debug!("Exiting Main");
     Ok(())
 }
+
+/// Open the history saved in the Jupyter data directory, falling back to an in-memory history if
+/// that is not possible; a broken history file should not stop the kernel from starting.
+fn open_history() -> History {
+    let Some(data_dir) = jupyter_data_dir() else {
+        println_debug!("Could not find the Jupyter data directory; history will not be saved");
+        return History::new();
+    };
+    History::open(data_dir.join("nickkerish").join("history.jsonl"))
+        .inspect_err(|err| println_debug!("Failed to open history; it will not be saved: {err:?}"))
+        .unwrap_or_default()
+}
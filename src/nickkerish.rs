@@ -0,0 +1,494 @@
+use nikkerish::{
+    comm::{Comm, CommHandler, Comms},
+    debugger::{Frame, Variable},
+    widgets::{
+        self,
+        models::{Checkbox, Dropdown, FloatSlider, IntSlider, Text},
+        Widget,
+        Widgets,
+    },
+    kernel::{ExecutionContext, Kernel},
+    protocol::{
+        ErrorDetails,
+        ExecuteRequest,
+        IsCompleteReply,
+        IsCompleteReplyStatus,
+        KernelInfoReply,
+        Payload,
+    },
+};
+
+use anyhow::{Context, Result};
+use bytes::Bytes;
+use serde_json::{json, Map, Value};
+use std::{
+    collections::HashMap,
+    sync::{Arc, Mutex, OnceLock},
+    time::Duration,
+};
+
+/// The Nickkerish language; it just echoes back whatever it is asked to execute.
+///
+/// Code is run one line at a time. A line which calls one of the built-ins below does what it
+/// says, anything else is echoed back. The value of the last line is the result of the cell.
+///
+/// - `input("prompt")` asks the user for a line of text
+/// - `display("text", "id")` shows the text as a separate output; the id is optional
+/// - `update_display("text", "id")` replaces the text of the output(s) displayed with the id
+/// - `clear()` clears the output of the cell; `clear(wait)` waits until there is new output to
+///   show first, to avoid flicker (`clear(true)` also works)
+/// - `sleep(milliseconds)` waits, e.g. between updates to a display
+/// - `raise("Name", "message")` fails with the given error
+/// - `interact("x is {x}", x=5)` shows a widget for each keyword argument, and the text below
+///   them, updated whenever the value of a widget changes; see [interact]
+///
+/// A few lines are commands for the frontend rather than code:
+///
+/// - `%load file.nick` replaces the cell with the contents of the file, ready to be run
+/// - `name?` shows the help for a built-in in the frontend's pager
+/// - `exit` asks the frontend to exit, e.g. `jupyter console`
+///
+/// Breakpoints can be set on any line with JupyterLab's debugger. While paused, the value of the
+/// previous line is shown as the variable `_`.
+///
+/// After each cell, `_` holds its result and `_i` its code. These are the only names
+/// `user_expressions` can refer to; an expression may also be a double quoted string.
+///
+/// Comms opened by the frontend with the target `nickkerish.echo` send every message back.
+#[derive(Default)]
+pub struct Nickkerish {
+    /// Set once the comm targets are registered
+    widgets: OnceLock<Widgets>,
+    /// The values of `_` and `_i`
+    namespace: Mutex<HashMap<String, String>>,
+}
+
+/// Handler for the `nickkerish.echo` comm target
+struct Echo;
+
+impl CommHandler for Echo {
+    fn on_message(&self, comm: &Comm, data: &Map<String, Value>, buffers: &[Bytes]) -> Result<()> {
+        comm.send_with_buffers(Value::Object(data.clone()), buffers.to_vec())
+    }
+}
+
+impl Kernel for Nickkerish {
+    fn kernel_info(&self) -> KernelInfoReply {
+        KernelInfoReply {
+            debugger: true,
+            ..Default::default()
+        }
+    }
+
+    async fn execute(&self, request: &ExecuteRequest, context: &ExecutionContext) -> Result<()> {
+        // like IPython, silent requests (e.g. probes sent by the frontend) leave `_i` and `_` alone
+        if !request.silent {
+            self.namespace.lock().unwrap().insert("_i".into(), request.code.clone());
+        }
+        let mut result = None;
+        let name = format!("Cell In[{}]", context.execution_count());
+        let source_path = context.debugger().source_path(&request.code);
+        for (index, line) in request.code.lines().enumerate() {
+            if line.trim().is_empty() {
+                continue;
+            }
+            context.trace(vec![Frame {
+                name: name.clone(),
+                source_path: source_path.clone(),
+                line: index + 1,
+                variables: result.iter().map(|result: &String| Variable {
+                    name: "_".into(),
+                    value: result.clone(),
+                    type_name: Some("str".into()),
+                }).collect(),
+            }]).await?;
+            result = run_line(line, context, self.widgets.get()).await.map_err(|err| {
+                ErrorDetails::from(err).with_frame(
+                    &name,
+                    &request.code,
+                    index + 1,
+                )
+            })?;
+        }
+        if let Some(result) = result {
+            context.execute_result(json!({"text/plain":result}))?;
+            if !request.silent {
+                self.namespace.lock().unwrap().insert("_".into(), result);
+            }
+        }
+        Ok(())
+    }
+
+    fn evaluate_user_expression(&self, expression: &str) -> Result<Value> {
+        let expression = expression.trim();
+        let value = match self.namespace.lock().unwrap().get(expression) {
+            Some(value) => value.clone(),
+            None if expression.starts_with('"') => serde_json::from_str(expression).map_err(|_| {
+                ErrorDetails::new("SyntaxError", format!("invalid string {expression}"))
+            })?,
+            None => return Err(ErrorDetails::new(
+                "NameError",
+                format!("name '{expression}' is not defined"),
+            ).into()),
+        };
+        Ok(json!({"text/plain":value}))
+    }
+
+    fn register_comm_targets(&self, comms: &Comms) {
+        comms.register_target("nickkerish.echo", |_: &Comm, _: &Value, _: &[Bytes]| {
+            Ok(Arc::new(Echo) as Arc<dyn CommHandler>)
+        });
+        let _ = self.widgets.set(Widgets::register(comms));
+    }
+
+    fn shutdown(&self, _restart: bool) {
+        if let Some(widgets) = self.widgets.get() {
+            widgets.clear();
+        }
+        self.namespace.lock().unwrap().clear();
+    }
+
+    fn is_complete(&self, _code: &str) -> IsCompleteReply {
+        IsCompleteReply {
+            status: IsCompleteReplyStatus::Complete,
+            indent: None,
+        }
+    }
+}
+
+/// Run a single line of Nickkerish, returning its value if it has one
+async fn run_line(line: &str, context: &ExecutionContext, widgets: Option<&Widgets>) -> Result<Option<String>> {
+    if let Some(command) = command(line) {
+        match command? {
+            Command::Payload(payload) => context.payload(payload),
+            Command::Print(text) => context.stream("stdout", &text)?,
+        }
+        return Ok(None);
+    }
+    let Some((name, arguments)) = parse_call(line) else {
+        return echo(line, context).map(Some);
+    };
+    let positional: Vec<&str> = arguments
+        .iter()
+        .filter(|argument| argument.keyword.is_none())
+        .map(|argument| argument.value.as_str())
+        .collect();
+    Ok(match (name, positional.as_slice()) {
+        ("input", []) | ("input", [_]) => {
+            let prompt = positional.first().copied().unwrap_or("");
+            Some(format!("{:?}", context.input(prompt, false).await?))
+        },
+        ("display", [text]) | ("display", [text, _]) => {
+            context.display_data(json!({"text/plain":text}), positional.get(1).copied())?;
+            None
+        },
+        ("update_display", [text, display_id]) => {
+            context.update_display_data(json!({"text/plain":text}), display_id)?;
+            None
+        },
+        ("clear", []) | ("clear", [_]) => {
+            context.clear_output(positional.first().is_some_and(|wait| matches!(*wait, "wait" | "true")))?;
+            None
+        },
+        ("sleep", [milliseconds]) => {
+            let milliseconds = milliseconds.parse().map_err(|_| ErrorDetails::new(
+                "ValueError",
+                format!("sleep expects a whole number of milliseconds, not {milliseconds:?}"),
+            ))?;
+            tokio::time::sleep(Duration::from_millis(milliseconds)).await;
+            None
+        },
+        ("raise", [name, message]) => {
+            return Err(ErrorDetails::new(*name, *message).into());
+        },
+        ("interact", [template]) => {
+            let widgets = widgets.context("Widgets are not available before the kernel has started")?;
+            let vbox = interact(widgets, template, &arguments)?;
+            context.display_data(vbox.mime_bundle(), None)?;
+            None
+        },
+        _ => Some(echo(line, context)?),
+    })
+}
+
+/// What a line which is a command for the frontend, rather than code, asks for
+#[derive(Debug, PartialEq)]
+enum Command {
+    /// Sent in the execute_reply, see [ExecutionContext::payload]
+    Payload(Payload),
+    /// Printed to stdout
+    Print(String),
+}
+
+/// Interpret `line` if it is one of the commands for the frontend listed on [Nickkerish]
+fn command(line: &str) -> Option<Result<Command>> {
+    let line = line.trim();
+    if line == "exit" {
+        return Some(Ok(Command::Payload(Payload::AskExit { keepkernel: false })));
+    }
+    if let Some(path) = line.strip_prefix("%load ") {
+        let path = path.trim();
+        return Some(match std::fs::read_to_string(path) {
+            // like IPython, keep the command as a comment so the cell can be loaded again
+            Ok(contents) => Ok(Command::Payload(Payload::SetNextInput {
+                text: format!("# %load {path}\n{contents}"),
+                replace: true,
+            })),
+            Err(err) => Err(ErrorDetails::new("OSError", format!("could not read {path}: {err}")).into()),
+        });
+    }
+    let name = line.strip_suffix('?').filter(|name| is_identifier(name))?;
+    Some(Ok(match help(name) {
+        Some(help) => Command::Payload(Payload::Page {
+            data: json!({"text/plain":help}),
+            start: 0,
+        }),
+        None => Command::Print(format!("Object `{name}` not found.\n")),
+    }))
+}
+
+/// The help shown for `name?`, if `name` is a built-in
+fn help(name: &str) -> Option<&'static str> {
+    Some(match name {
+        "input" => "input(\"prompt\")\n\nAsk the user for a line of text, showing the prompt, which is optional.",
+        "display" => "display(\"text\", \"id\")\n\nShow the text as a separate output. Give an id to be able to replace it later with update_display.",
+        "update_display" => "update_display(\"text\", \"id\")\n\nReplace the text of every output displayed with the id.",
+        "clear" => "clear(wait)\n\nClear the output of the cell. With wait, the old output is kept until there is new output to show.",
+        "sleep" => "sleep(milliseconds)\n\nWait for the given number of milliseconds.",
+        "raise" => "raise(\"Name\", \"message\")\n\nFail with the given error.",
+        "interact" => "interact(\"x is {x}\", x=5)\n\nShow a control for each keyword argument, and the text below them with each {name} replaced by the value of its control.",
+        "exit" => "exit\n\nAsk the frontend to exit.",
+        _ => return None,
+    })
+}
+
+/// Echo a line which is not a call to a built-in back to the frontend
+fn echo(line: &str, context: &ExecutionContext) -> Result<String> {
+    let echo = format!(
+        "You tried to execute `{:?}`, but Nickkerish is a dummy kernel, and does not do what you want!",
+        line
+    );
+    context.stream("stdout", &echo)?;
+    Ok(echo)
+}
+
+/// Show a control for each keyword argument, and `template` below them with each `{name}`
+/// replaced by the value of the control for `name`. The kind of control depends on the value of
+/// the argument:
+///
+/// - `"text"` gives a text box
+/// - `true` or `false` gives a checkbox
+/// - a whole number gives an integer slider, a decimal number a float slider
+/// - `a|b|c` gives a drop down list of the options
+fn interact(widgets: &Widgets, template: &str, arguments: &[Argument]) -> Result<Widget> {
+    let mut controls = Vec::new();
+    for argument in arguments {
+        let Some(name) = &argument.keyword else { continue };
+        controls.push((name.clone(), control(widgets, name, argument)?));
+    }
+    let template = template.to_owned();
+    widgets::interact(widgets, controls, move |values| {
+        let mut text = template.clone();
+        for (name, value) in values {
+            let value = match value {
+                Value::String(value) => value.clone(),
+                value => value.to_string(),
+            };
+            text = text.replace(&format!("{{{name}}}"), &value);
+        }
+        Ok(json!({"text/plain":text}))
+    })
+}
+
+/// The range of an [IntSlider] starting at `value`, with room to move either way
+fn int_range(value: i64) -> (i64, i64) {
+    match value {
+        value if value > 0 => (-value, value.saturating_mul(3)),
+        value if value < 0 => (value.saturating_mul(3), value.saturating_neg()),
+        _ => (-1, 1),
+    }
+}
+
+/// Create the control `interact` shows for one keyword argument
+fn control(widgets: &Widgets, name: &str, argument: &Argument) -> Result<Widget> {
+    let description = name.to_owned();
+    let value = argument.value.as_str();
+    if argument.quoted {
+        return widgets.create(&Text { value: value.to_owned(), description, ..Default::default() });
+    }
+    if let Ok(value) = value.parse::<bool>() {
+        return widgets.create(&Checkbox { value, description, ..Default::default() });
+    }
+    if let Ok(value) = value.parse::<i64>() {
+        let (min, max) = int_range(value);
+        return widgets.create(&IntSlider { value, min, max, description, ..Default::default() });
+    }
+    if let Ok(value) = value.parse::<f64>() {
+        let (min, max) = match value {
+            value if value > 0.0 => (-value, 3.0 * value),
+            value if value < 0.0 => (3.0 * value, -value),
+            _ => (-1.0, 1.0),
+        };
+        return widgets.create(&FloatSlider { value, min, max, step: 0.1, description, ..Default::default() });
+    }
+    if value.contains('|') {
+        let options: Vec<String> = value.split('|').map(|option| option.trim().to_owned()).collect();
+        return widgets.create(&Dropdown { options, index: Some(0), description, ..Default::default() });
+    }
+    Err(ErrorDetails::new(
+        "ValueError",
+        format!("interact cannot make a control for {name}={value}"),
+    ).into())
+}
+
+/// One argument of a call; see [parse_call]
+#[derive(Debug, Clone, PartialEq)]
+struct Argument {
+    /// The name of a keyword argument like `name="value"`
+    keyword: Option<String>,
+    value: String,
+    /// Whether the value was a double quoted string
+    quoted: bool,
+}
+
+impl Argument {
+    #[cfg(test)]
+    fn new(keyword: Option<&str>, value: &str, quoted: bool) -> Self {
+        Self { keyword: keyword.map(str::to_owned), value: value.to_owned(), quoted }
+    }
+}
+
+/// Split a line like `name("some text", 12, size=3)` into the name of the function and its
+/// arguments.
+///
+/// Arguments are either double quoted strings (which may contain `\"` and `\\` escapes) or bare
+/// words, which are taken as they are. Either may be preceded by `keyword=`.
+fn parse_call(line: &str) -> Option<(&str, Vec<Argument>)> {
+    let (name, rest) = line.trim().split_once('(')?;
+    let name = name.trim_end();
+    if !is_identifier(name) {
+        return None;
+    }
+    let mut chars = rest.strip_suffix(')')?.chars().peekable();
+    let mut arguments = Vec::new();
+    loop {
+        while chars.next_if(|c| c.is_whitespace()).is_some() {}
+        if chars.peek().is_none() {
+            break;
+        }
+        let mut keyword = None;
+        let mut lookahead = chars.clone();
+        let mut word = String::new();
+        while let Some(c) = lookahead.next_if(|c| c.is_alphanumeric() || *c == '_') {
+            word.push(c);
+        }
+        while lookahead.next_if(|c| c.is_whitespace()).is_some() {}
+        if !word.is_empty() && lookahead.next_if_eq(&'=').is_some() {
+            keyword = Some(word);
+            chars = lookahead;
+            while chars.next_if(|c| c.is_whitespace()).is_some() {}
+        }
+        let mut value = String::new();
+        let quoted = chars.next_if_eq(&'"').is_some();
+        if quoted {
+            loop {
+                match chars.next()? {
+                    '"' => break,
+                    '\\' => value.push(chars.next()?),
+                    c => value.push(c),
+                }
+            }
+        } else {
+            while let Some(c) = chars.next_if(|c| *c != ',') {
+                value.push(c);
+            }
+            value.truncate(value.trim_end().len());
+        }
+        arguments.push(Argument { keyword, value, quoted });
+        while chars.next_if(|c| c.is_whitespace()).is_some() {}
+        match chars.next() {
+            None => break,
+            Some(',') => continue,
+            Some(_) => return None,
+        }
+    }
+    Some((name, arguments))
+}
+
+fn is_identifier(name: &str) -> bool {
+    !name.is_empty() && name.chars().all(|c| c.is_alphanumeric() || c == '_')
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_call() {
+        assert_eq!(parse_call("input()"), Some(("input", vec![])));
+        assert_eq!(
+            parse_call(r#" input("Name: ") "#),
+            Some(("input", vec![Argument::new(None, "Name: ", true)]))
+        );
+        assert_eq!(
+            parse_call(r#"f("a, \"b\"", 12 , x y)"#),
+            Some(("f", vec![
+                Argument::new(None, r#"a, "b""#, true),
+                Argument::new(None, "12", false),
+                Argument::new(None, "x y", false),
+            ]))
+        );
+        assert_eq!(
+            parse_call(r#"interact("{a}", a = 1, b="x", c=p|q)"#),
+            Some(("interact", vec![
+                Argument::new(None, "{a}", true),
+                Argument::new(Some("a"), "1", false),
+                Argument::new(Some("b"), "x", true),
+                Argument::new(Some("c"), "p|q", false),
+            ]))
+        );
+        assert_eq!(parse_call("print a"), None);
+        assert_eq!(parse_call("a + (b)"), None);
+        assert_eq!(parse_call(r#"input("unterminated)"#), None);
+    }
+
+    #[test]
+    fn test_int_range() {
+        assert_eq!(int_range(5), (-5, 15));
+        assert_eq!(int_range(-5), (-15, 5));
+        assert_eq!(int_range(0), (-1, 1));
+        assert_eq!(int_range(i64::MAX), (-i64::MAX, i64::MAX));
+        assert_eq!(int_range(i64::MIN), (i64::MIN, i64::MAX));
+    }
+
+    #[test]
+    fn test_evaluate_user_expression() {
+        let kernel = Nickkerish::default();
+        kernel.namespace.lock().unwrap().insert("_".into(), "3".into());
+        assert_eq!(kernel.evaluate_user_expression(" _ ").unwrap(), json!({"text/plain": "3"}));
+        assert_eq!(kernel.evaluate_user_expression(r#""hi""#).unwrap(), json!({"text/plain": "hi"}));
+        let error_name = |expression| ErrorDetails::from(kernel.evaluate_user_expression(expression).unwrap_err()).error_name;
+        assert_eq!(error_name("x"), "NameError");
+        assert_eq!(error_name(r#""unterminated"#), "SyntaxError");
+    }
+
+    #[test]
+    fn test_command() {
+        assert_eq!(command(" exit ").unwrap().unwrap(), Command::Payload(Payload::AskExit { keepkernel: false }));
+
+        let path = std::env::temp_dir().join(format!("nickkerish-{}.nick", uuid::Uuid::new_v4()));
+        let load = format!("%load {}", path.display());
+        std::fs::write(&path, "a\nb").unwrap();
+        assert_eq!(command(&load).unwrap().unwrap(), Command::Payload(Payload::SetNextInput {
+            text: format!("# {load}\na\nb"),
+            replace: true,
+        }));
+        std::fs::remove_file(&path).unwrap();
+        assert_eq!(ErrorDetails::from(command(&load).unwrap().unwrap_err()).error_name, "OSError");
+
+        assert!(matches!(command("input?"), Some(Ok(Command::Payload(Payload::Page { .. })))));
+        assert_eq!(command("nope?").unwrap().unwrap(), Command::Print("Object `nope` not found.\n".into()));
+        assert!(command("input()").is_none());
+        assert!(command("a ? b").is_none());
+    }
+}
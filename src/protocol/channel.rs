@@ -0,0 +1,138 @@
+use super::{
+    MessageContent,
+    KernelInfoRequest,
+    KernelInfoReply,
+    HistoryRequest,
+    ExecuteRequest,
+    InspectRequest,
+    CompleteRequest,
+    IsCompleteRequest,
+    CommInfoRequest,
+    ShutdownRequest,
+    InterruptRequest,
+    DebugRequest,
+    CreateSubshellRequest,
+    DeleteSubshellRequest,
+    ListSubshellRequest,
+    InputRequest,
+    HistoryReply,
+    ExecuteReply,
+    InspectReply,
+    CompleteReply,
+    IsCompleteReply,
+    CommInfoReply,
+    ShutdownReply,
+    InterruptReply,
+    DebugReply,
+    CreateSubshellReply,
+    DeleteSubshellReply,
+    ListSubshellReply,
+    StatusPublication,
+    StreamPublication,
+    ExecuteInputPublication,
+    ExecuteResultPublication,
+    DisplayDataPublication,
+    UpdateDisplayDataPublication,
+    ClearOutputPublication,
+    ErrorPublication,
+    DebugEventPublication,
+    CommOpen,
+    CommMsg,
+    CommClose,
+};
+
+/// Content the client sends on the shell or control socket, which the kernel answers with a
+/// [Request::Reply] on the same socket.
+pub trait Request: Into<MessageContent> {
+    type Reply: Reply;
+}
+
+/// Content the kernel sends back on the shell or control socket, in answer to the request the
+/// client sent on the same socket.
+///
+/// Only these can be passed to [Session::reply](crate::server::Session::reply), which takes the
+/// `msg_type` of the reply from the content, and only accepts the reply which matches the
+/// [Request], so a reply can neither end up on iopub nor answer the wrong request.
+pub trait Reply: Into<MessageContent> {}
+
+/// Content the kernel broadcasts to every client on the iopub socket.
+///
+/// Only these can be passed to [Iopub::publish](crate::server::Iopub::publish).
+pub trait Publication: Into<MessageContent> {}
+
+/// Content the kernel sends to a single client on the stdin socket, which the client answers on
+/// the same socket.
+///
+/// Only these can be passed to [Session::stdin_request](crate::server::Session::stdin_request).
+pub trait StdinRequest: Into<MessageContent> {}
+
+macro_rules! impl_marker {
+    ($trait:ident for $($type:ident),*) => {
+        $(
+            impl $trait for $type {}
+        )*
+    }
+}
+
+macro_rules! impl_request {
+    ($($request:ident => $reply:ident),*) => {
+        $(
+            impl Request for $request {
+                type Reply = $reply;
+            }
+        )*
+    }
+}
+
+impl_request!(
+    KernelInfoRequest     => KernelInfoReply,
+    HistoryRequest        => HistoryReply,
+    ExecuteRequest        => ExecuteReply,
+    InspectRequest        => InspectReply,
+    CompleteRequest       => CompleteReply,
+    IsCompleteRequest     => IsCompleteReply,
+    CommInfoRequest       => CommInfoReply,
+    ShutdownRequest       => ShutdownReply,
+    InterruptRequest      => InterruptReply,
+    DebugRequest          => DebugReply,
+    CreateSubshellRequest => CreateSubshellReply,
+    DeleteSubshellRequest => DeleteSubshellReply,
+    ListSubshellRequest   => ListSubshellReply
+);
+
+impl_marker!(Reply for
+    KernelInfoReply,
+    HistoryReply,
+    ExecuteReply,
+    InspectReply,
+    CompleteReply,
+    IsCompleteReply,
+    CommInfoReply,
+    ShutdownReply,
+    InterruptReply,
+    DebugReply,
+    CreateSubshellReply,
+    DeleteSubshellReply,
+    ListSubshellReply
+);
+
+// Comm messages travel on shell from the client to the kernel, but on iopub from the kernel to the
+// client
+impl_marker!(Publication for
+    StatusPublication,
+    StreamPublication,
+    ExecuteInputPublication,
+    ExecuteResultPublication,
+    DisplayDataPublication,
+    UpdateDisplayDataPublication,
+    ClearOutputPublication,
+    ErrorPublication,
+    DebugEventPublication,
+    CommOpen,
+    CommMsg,
+    CommClose
+);
+
+impl_marker!(StdinRequest for
+    InputRequest
+);
@@ -9,5 +9,9 @@ pub struct Header{
     pub username:String,
     pub session:String,
     pub date:String,
-    pub version:String
+    pub version:String,
+    /// The subshell a shell request should run in; absent for the main shell. See
+    /// [Kernel subshells](https://jupyter.org/enhancement-proposals/91-kernel-subshells/kernel-subshells.html)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub subshell_id:Option<String>,
 }
\ No newline at end of file
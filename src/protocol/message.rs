@@ -1,7 +1,7 @@
 use super::{
-    HmacSha256,
     Header,
     MessageContent,
+    SigningKey,
     DELIMITER
 };
 use crate::util::EmptyObjectOr;
@@ -10,31 +10,20 @@ use crate::util::TryToJsonBytesString;
 
 use anyhow::Result;
 use bytes::Bytes;
-use clio::Input;
-use hmac::Mac;
 use zeromq::ZmqMessage;
 
-/// Compute the signature for the message
-/// 
-/// TODO: The 
-fn compute_signature(
-    key           : &str, 
-    header        : &Bytes, 
-    parent_header : &Bytes, 
-    metadata      : &Bytes, 
-    content       : &Bytes, 
-    extra_buffers : &Vec<Bytes>
-) -> Result<Bytes> {
-    let mut mac = HmacSha256::new_from_slice(key.as_bytes())?;
-    mac.update(header);
-    mac.update(parent_header);
-    mac.update(metadata);
-    mac.update(content);
-    for buffer in extra_buffers {
-        mac.update(buffer);
-    }
-    let mac:Vec<u8> = mac.finalize().into_bytes().to_vec();
-    Ok(mac.into())
+/// The parts of a message which are signed, in order
+fn signed_parts<'a>(
+    header        : &'a Bytes,
+    parent_header : &'a Bytes,
+    metadata      : &'a Bytes,
+    content       : &'a Bytes,
+    extra_buffers : &'a [Bytes],
+) -> impl Iterator<Item = &'a [u8]> {
+    [header, parent_header, metadata, content]
+        .into_iter()
+        .chain(extra_buffers)
+        .map(|part| &part[..])
 }
 
 #[derive(Debug, Clone)]
@@ -71,31 +60,30 @@ pub struct MessageBytes {
 }
 
 impl MessageBytes{
-    fn validate_signature(&self, key: &str) -> Result<()> {
-        let signature = compute_signature(
-            &key,
+    fn validate_signature(&self, key: &SigningKey) -> Result<()> {
+        key.verify(&self.signature, signed_parts(
             &self.header,
             &self.parent_header,
             &self.metadata,
             &self.content,
             &self.extra_buffers,
-        )?;
-        let signature = hex::encode(signature);
-        if signature == self.signature {
-            Ok(())
-        } else {
-            Err(anyhow::anyhow!(format!("Signature validation failed {:?} != {:?}",signature, self.signature)))
-        }
+        ))
     }
-    pub fn decode(self, key:&str) -> Result<MessageParsed> {
+    /// Validate the signature and deserialize the message.
+    ///
+    /// The content is deserialized according to the `msg_type` in the header (see
+    /// [MessageContent::deserialize_as]), so a content which does not match the header is an error.
+    pub fn decode(self, key: &SigningKey) -> Result<MessageParsed> {
         self.validate_signature(key)?;
+        let header: Header = TryFromJsonBytesString::try_from_json_bytes(&self.header)?;
+        let content = MessageContent::deserialize_as(&header.message_type, &self.content)?;
         Ok(MessageParsed{
-            key           : key.to_owned(),
+            key           : key.clone(),
             identities    : self.identities,
-            header        : TryFromJsonBytesString::try_from_json_bytes(&self.header)?,
+            header,
             parent_header : TryFromJsonBytesString::try_from_json_bytes(&self.parent_header)?,
             metadata      : TryFromJsonBytesString::try_from_json_bytes(&self.metadata)?,
-            content       : TryFromJsonBytesString::try_from_json_bytes(&self.content)?,
+            content,
             extra_buffers : self.extra_buffers,
         })
     }
@@ -118,11 +106,16 @@ impl std::fmt::Display for MessageBytes {
     }
 }
 
-impl From<ZmqMessage> for MessageBytes {
-    fn from(message: ZmqMessage) -> Self {
+impl TryFrom<ZmqMessage> for MessageBytes {
+    type Error = anyhow::Error;
+    fn try_from(message: ZmqMessage) -> Result<Self> {
         let message = message.into_vec();
-        let delimiter_index = message.iter().position(|frame| frame == &DELIMITER).unwrap();
-        MessageBytes {
+        let delimiter_index = message.iter().position(|frame| frame == DELIMITER)
+            .ok_or_else(|| anyhow::anyhow!("Message does not contain the delimiter {DELIMITER:?}"))?;
+        if message.len() < delimiter_index + 6 {
+            anyhow::bail!("Message has too few frames after the delimiter");
+        }
+        Ok(MessageBytes {
             identities    : message[0..delimiter_index].into(),
             signature     : message[delimiter_index + 1].clone(),
             header        : message[delimiter_index + 2].clone(),
@@ -130,32 +123,32 @@ impl From<ZmqMessage> for MessageBytes {
             metadata      : message[delimiter_index + 4].clone(),
             content       : message[delimiter_index + 5].clone(),
             extra_buffers : message[delimiter_index + 6..].into(),
-        }
+        })
     }
 }
 
-impl Into<ZmqMessage> for MessageBytes {
-    fn into(self) -> ZmqMessage {
+impl From<MessageBytes> for ZmqMessage {
+    fn from(message: MessageBytes) -> Self {
         let mut frames = Vec::new();
-        frames.extend(self.identities);
+        frames.extend(message.identities);
         frames.push(DELIMITER.into());
-        frames.push(self.signature);
-        frames.push(self.header);
-        frames.push(self.parent_header);
-        frames.push(self.metadata);
-        frames.push(self.content);
-        frames.extend(self.extra_buffers);
+        frames.push(message.signature);
+        frames.push(message.header);
+        frames.push(message.parent_header);
+        frames.push(message.metadata);
+        frames.push(message.content);
+        frames.extend(message.extra_buffers);
         // NOTE: Empty Message Error is not possible since `frames.len()>0`
         ZmqMessage::try_from(frames).unwrap()
     }
 }
 
 /// A deserialized ZMQ Jupyter Message
-#[derive(Debug, Default)]
+#[derive(Debug, Clone)]
 pub struct MessageParsed {
     
     /// The key which will/was used to to sign the message
-    pub key: String,
+    pub key: SigningKey,
 
     /// Identities are part of the ZMQ protocol and are used for routing.
     /// We don't know why multiple identities might be needed? The whole delimiter business is very annoying.
@@ -163,7 +156,7 @@ pub struct MessageParsed {
     pub identities: Vec<Bytes>,
     
     /// the header for this message
-    pub header: EmptyObjectOr<Header>,
+    pub header: Header,
 
     /// A copy of the header from the message that 'caused' this message
     pub parent_header: EmptyObjectOr<Header>,
@@ -171,8 +164,8 @@ pub struct MessageParsed {
     /// Any valid JSON inside an object, or an empty object {}
     pub metadata: serde_json::Map<String, serde_json::Value>,
 
-    /// Any valid JSON message content inside an object, or an empty object {}
-    pub content: EmptyObjectOr<MessageContent>,
+    /// The message content, of the type specified by `header.msg_type`
+    pub content: MessageContent,
 
     /// Raw data buffers, which can be used by message types that support binary data such as comms
     /// and extensions to the protocol.
@@ -201,56 +194,55 @@ fn format_json_object_string(inp:String) -> String {
 
 impl MessageParsed {
     pub fn new(
-        key: String,
+        key: SigningKey,
         identities: Vec<Bytes>,
-        header: EmptyObjectOr<Header>,
+        header: Header,
         parent_header: EmptyObjectOr<Header>,
         metadata: serde_json::Map<String, serde_json::Value>,
-        content: EmptyObjectOr<MessageContent>,
+        content: MessageContent,
         extra_buffers: Vec<Bytes>,
     ) -> Self {
         MessageParsed {
-            key           : key,
-            identities    : identities,
-            header        : header,
-            parent_header : parent_header,
-            metadata      : metadata,
-            content       : content,
-            extra_buffers : extra_buffers,
+            key,
+            identities,
+            header,
+            parent_header,
+            metadata,
+            content,
+            extra_buffers,
         }
     }
 
-    pub fn reply(
+    fn reply(
         &self,
         header: Header,
-        content: EmptyObjectOr<MessageContent>,
+        content: MessageContent,
         metadata: serde_json::Map<String, serde_json::Value>,
         extra_buffers: Vec<Bytes>,
     ) -> MessageParsed {
         MessageParsed{
             key: self.key.clone(),
             identities: self.identities.clone(),
-            header: EmptyObjectOr::Object(header),
-            parent_header: self.header.clone(),
-            metadata: metadata,
-            content: content,
-            extra_buffers: extra_buffers,
+            header,
+            parent_header: self.header.clone().into(),
+            metadata,
+            content,
+            extra_buffers,
         }
     }
 
     pub fn encode(self) -> Result<MessageBytes> {
-        let header        = (&self.header       ).try_to_json_bytes()?;
-        let parent_header = (&self.parent_header).try_to_json_bytes()?;
-        let metadata      = (&self.metadata     ).try_to_json_bytes()?;
-        let content       = (&self.content      ).try_to_json_bytes()?;
-        let signature = Bytes::from(hex::encode(compute_signature(
-            &self.key,
+        let header        = self.header       .try_to_json_bytes()?;
+        let parent_header = self.parent_header.try_to_json_bytes()?;
+        let metadata      = self.metadata     .try_to_json_bytes()?;
+        let content       = self.content      .try_to_json_bytes()?;
+        let signature = self.key.sign(signed_parts(
             &header,
             &parent_header,
             &metadata,
             &content,
-            &self.extra_buffers
-        )?));
+            &self.extra_buffers,
+        ))?;
         Ok(MessageBytes{
             identities    : self.identities.clone(),
             signature     ,
@@ -262,4 +254,41 @@ impl MessageParsed {
         })
     }
     
-}
\ No newline at end of file
+}
+
+/// A reply to a request, ready to be sent back to the client on the socket the request arrived on.
+///
+/// Created by [Session::reply](crate::server::Session::reply), so that only replies can be queued
+/// for the shell and control sockets.
+#[derive(Debug, Clone)]
+pub struct ReplyMessage(MessageParsed);
+
+impl ReplyMessage {
+    pub(crate) fn new(request: &MessageParsed, header: Header, content: MessageContent) -> Self {
+        Self(request.reply(header, content, Default::default(), Default::default()))
+    }
+
+    /// Set the metadata of the reply
+    pub fn with_metadata(mut self, metadata: serde_json::Map<String, serde_json::Value>) -> Self {
+        self.0.metadata = metadata;
+        self
+    }
+
+    pub fn encode(self) -> Result<MessageBytes> {
+        self.0.encode()
+    }
+}
+
+impl std::ops::Deref for ReplyMessage {
+    type Target = MessageParsed;
+
+    fn deref(&self) -> &MessageParsed {
+        &self.0
+    }
+}
+
+impl std::fmt::Display for ReplyMessage {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        self.0.fmt(f)
+    }
+}
@@ -1,7 +1,10 @@
 use super::{
+    MessageType,
     HistoryRequest,
+    HistoryReply,
     IsCompleteReply,
     IsCompleteRequest,
+    KernelInfoRequest,
     KernelInfoReply,
     StatusPublication,
     ExecuteRequest,
@@ -12,19 +15,74 @@ use super::{
     CommMsg, 
     ExecuteInputPublication,
     StreamPublication,
+    CompleteRequest,
+    CompleteReply,
+    InspectRequest,
+    InspectReply,
+    ShutdownRequest,
+    ShutdownReply,
+    InterruptRequest,
+    InterruptReply,
+    InputRequest,
+    InputReply,
+    DisplayDataPublication,
+    UpdateDisplayDataPublication,
+    ClearOutputPublication,
+    ErrorPublication,
+    CommInfoRequest,
+    CommInfoReply,
+    DebugRequest,
+    DebugReply,
+    DebugEventPublication,
+    CreateSubshellRequest,
+    CreateSubshellReply,
+    DeleteSubshellRequest,
+    DeleteSubshellReply,
+    ListSubshellRequest,
+    ListSubshellReply,
 };
-use serde::{Deserialize, Serialize};
+use anyhow::{Context, Result};
+use serde::Serialize;
 
 macro_rules! define_message_content_and_impl_from {
-    ($($type:tt),*) => {
-        #[derive(Debug, Serialize, Deserialize, PartialEq)]
+    ($($message_type:ident => $type:ident),*) => {
+        /// The content of a message. The variant is determined by the `msg_type` in the header of
+        /// the message; see [MessageContent::deserialize_as]
+        #[derive(Debug, Serialize, PartialEq, Clone)]
         #[serde(untagged)]
+        #[allow(clippy::large_enum_variant)]
         pub enum MessageContent {
             $(
                 $type($type),
             )*
         }
 
+        impl MessageContent {
+            /// Deserialize `content` as the content type which corresponds to `message_type`.
+            ///
+            /// Fails if the content does not match the structure required by the `message_type`.
+            pub fn deserialize_as(message_type: &MessageType, content: &[u8]) -> Result<Self> {
+                match message_type {
+                    $(
+                        MessageType::$message_type => Ok(MessageContent::$type(
+                            serde_json::from_slice(content).with_context(|| format!(
+                                "Content does not match the message type {message_type:?}"
+                            ))?
+                        )),
+                    )*
+                }
+            }
+
+            /// The `msg_type` which corresponds to this content
+            pub fn message_type(&self) -> MessageType {
+                match self {
+                    $(
+                        MessageContent::$type(_) => MessageType::$message_type,
+                    )*
+                }
+            }
+        }
+
         $(
             impl From<$type> for MessageContent {
                 fn from(item: $type) -> Self {
@@ -35,19 +93,46 @@ macro_rules! define_message_content_and_impl_from {
     }
 }
 define_message_content_and_impl_from!(
-    KernelInfoReply,
-    HistoryRequest,
-    ExecuteRequest,
-    ExecuteReply,
-    ExecuteInputPublication,
-    ExecuteResultPublication,
-    StatusPublication,
-    StreamPublication,
-    IsCompleteRequest,
-    IsCompleteReply,
-    CommOpen,
-    CommClose,
-    CommMsg
+    KernelInfoRequest     => KernelInfoRequest,
+    KernelInfoReply       => KernelInfoReply,
+    HistoryRequest        => HistoryRequest,
+    HistoryReply          => HistoryReply,
+    ExecuteRequest        => ExecuteRequest,
+    ExecuteReply          => ExecuteReply,
+    ExecuteInput          => ExecuteInputPublication,
+    ExecuteResult         => ExecuteResultPublication,
+    Status                => StatusPublication,
+    Stream                => StreamPublication,
+    InspectRequest        => InspectRequest,
+    InspectReply          => InspectReply,
+    CompleteRequest       => CompleteRequest,
+    CompleteReply         => CompleteReply,
+    IsCompleteRequest     => IsCompleteRequest,
+    IsCompleteReply       => IsCompleteReply,
+    CommOpen              => CommOpen,
+    CommClose             => CommClose,
+    CommMsg               => CommMsg,
+    CommInfoRequest       => CommInfoRequest,
+    CommInfoReply         => CommInfoReply,
+    ShutdownRequest       => ShutdownRequest,
+    ShutdownReply         => ShutdownReply,
+    InterruptRequest      => InterruptRequest,
+    InterruptReply        => InterruptReply,
+    InputRequest          => InputRequest,
+    InputReply            => InputReply,
+    DisplayData           => DisplayDataPublication,
+    UpdateDisplayData     => UpdateDisplayDataPublication,
+    ClearOutput           => ClearOutputPublication,
+    Error                 => ErrorPublication,
+    DebugRequest          => DebugRequest,
+    DebugReply            => DebugReply,
+    CreateSubshellRequest => CreateSubshellRequest,
+    CreateSubshellReply   => CreateSubshellReply,
+    DeleteSubshellRequest => DeleteSubshellRequest,
+    DeleteSubshellReply   => DeleteSubshellReply,
+    ListSubshellRequest   => ListSubshellRequest,
+    ListSubshellReply     => ListSubshellReply,
+    DebugEvent            => DebugEventPublication
 );
 
 
@@ -59,7 +144,47 @@ mod tests {
     fn test_history_request() {
         let data =
             b"{\"raw\": true, \"output\": false, \"hist_access_type\": \"tail\", \"n\": 1000}";
-        let history_request: MessageContent = serde_json::from_slice(data).unwrap();
-        println!("{:?}", history_request);
+        let history_request = MessageContent::deserialize_as(&MessageType::HistoryRequest, data).unwrap();
+        assert_eq!(history_request.message_type(), MessageType::HistoryRequest);
+    }
+
+    /// `comm_msg` and `comm_close` have identical fields, so the variant must come from the header
+    #[test]
+    fn test_deserialize_as_uses_message_type() {
+        let data = br#"{"comm_id": "abc", "data": {}}"#;
+        let comm_msg = MessageContent::deserialize_as(&MessageType::CommMsg, data).unwrap();
+        assert!(matches!(comm_msg, MessageContent::CommMsg(_)));
+        let comm_close = MessageContent::deserialize_as(&MessageType::CommClose, data).unwrap();
+        assert!(matches!(comm_close, MessageContent::CommClose(_)));
+    }
+
+    #[test]
+    fn test_deserialize_as_rejects_mismatched_content() {
+        let data = br#"{"code": "print(1)"}"#;
+        assert!(MessageContent::deserialize_as(&MessageType::ExecuteRequest, data).is_err());
+        assert!(MessageContent::deserialize_as(&MessageType::IsCompleteRequest, data).is_ok());
+    }
+
+    #[test]
+    fn test_kernel_info_request_is_empty_object() {
+        let content = MessageContent::deserialize_as(&MessageType::KernelInfoRequest, b"{}").unwrap();
+        assert_eq!(serde_json::to_string(&content).unwrap(), "{}");
+    }
+
+    #[test]
+    fn test_display_data_transient() {
+        let content = MessageContent::deserialize_as(
+            &MessageType::DisplayData,
+            br#"{"data": {"text/plain": "1"}, "metadata": {}}"#,
+        ).unwrap();
+        assert_eq!(
+            serde_json::to_string(&content).unwrap(),
+            r#"{"data":{"text/plain":"1"},"metadata":{},"transient":{}}"#
+        );
+        // update_display_data is meaningless without a display_id
+        assert!(MessageContent::deserialize_as(
+            &MessageType::UpdateDisplayData,
+            br#"{"data": {"text/plain": "1"}, "metadata": {}}"#,
+        ).is_err());
     }
 }
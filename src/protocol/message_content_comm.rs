@@ -1,17 +1,19 @@
-
-/// Message spec 4.1 (IPython 2.0) added a messaging system for developers to add their own objects
-/// with Frontend and Kernel-side components, and allow them to communicate with each other. To do
-/// this, IPython adds a notion of a Comm, which exists on both sides, and can communicate in either
-/// direction.
-///
-/// These messages are fully symmetrical - both the Kernel and the Frontend can send each message,
-/// and no messages expect a reply. The Kernel listens for these messages on the Shell channel, and
-/// the Frontend listens for them on the IOPub channel.
-/// 
-/// Since comm messages can execute arbitrary user code, handlers should set the parent header and
-/// publish status busy / idle, just like an execute request.
+//! Message spec 4.1 (IPython 2.0) added a messaging system for developers to add their own objects
+//! with Frontend and Kernel-side components, and allow them to communicate with each other. To do
+//! this, IPython adds a notion of a Comm, which exists on both sides, and can communicate in either
+//! direction.
+//!
+//! These messages are fully symmetrical - both the Kernel and the Frontend can send each message,
+//! and no messages expect a reply. The Kernel listens for these messages on the Shell channel, and
+//! the Frontend listens for them on the IOPub channel.
+//! 
+//! Since comm messages can execute arbitrary user code, handlers should set the parent header and
+//! publish status busy / idle, just like an execute request.
 
 use serde::{Serialize, Deserialize};
+use std::collections::HashMap;
+
+use super::ReplyStatus;
 
 
 /// Every Comm has an ID and a target name. The code handling the message on the receiving side is
@@ -21,17 +23,20 @@ use serde::{Serialize, Deserialize};
 /// 
 /// If the target_name key is not found on the receiving side, then it should immediately reply with
 /// a [comm_close](CommClose) message to avoid an inconsistent state.
-#[derive(Serialize, Deserialize, Debug, PartialEq)]
+#[derive(Serialize, Deserialize, Debug, PartialEq, Clone)]
 pub struct CommOpen {
     pub comm_id: String,
     pub target_name: String,
-    pub data: serde_json::Value
+    pub data: serde_json::Value,
+    /// Optionally, the module in which the target is to be found
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub target_module: Option<String>,
 }
 
 
 /// Since comms live on both sides, when a comm is destroyed the other side must be notified.
 /// This is done with a comm_close message.
-#[derive(Serialize, Deserialize, Debug, PartialEq)]
+#[derive(Serialize, Deserialize, Debug, PartialEq, Clone)]
 pub struct CommClose {
     pub comm_id: String,
     pub data: serde_json::Map<String, serde_json::Value>,
@@ -45,8 +50,34 @@ pub struct CommClose {
 /// dict.
 /// 
 /// There are no expected replies (of course, one side can send another comm_msg in reply).
-#[derive(Serialize, Deserialize, Debug, PartialEq)]
+#[derive(Serialize, Deserialize, Debug, PartialEq, Clone)]
 pub struct CommMsg {
     pub comm_id: String,
     pub data: serde_json::Map<String, serde_json::Value>,
 }
+
+
+/// When a client needs the currently open comms in the kernel, it can issue a request for the
+/// currently open comms.
+#[derive(Serialize, Deserialize, Debug, PartialEq, Clone)]
+pub struct CommInfoRequest {
+    /// Optional, the target name. If present, only the comms with this target are returned.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub target_name: Option<String>,
+}
+
+/// Describes one of the comms in a [CommInfoReply]
+#[derive(Serialize, Deserialize, Debug, PartialEq, Clone)]
+pub struct CommInfo {
+    pub target_name: String,
+}
+
+/// Reply to a [CommInfoRequest]
+#[derive(Serialize, Deserialize, Debug, PartialEq, Clone)]
+pub struct CommInfoReply {
+    /// 'ok' if the request succeeded or 'error', with error information as in all other replies.
+    pub status: ReplyStatus,
+
+    /// A dictionary of the comms, indexed by uuids.
+    pub comms: HashMap<String, CommInfo>,
+}
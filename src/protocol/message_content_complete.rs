@@ -0,0 +1,48 @@
+use serde::{Deserialize, Serialize};
+
+use super::ReplyStatus;
+
+/// Sent by the client to request tab completion of the code at the cursor position.
+#[derive(Serialize, Deserialize, Debug, PartialEq, Clone)]
+pub struct CompleteRequest {
+    /// The code context in which completion is requested
+    /// this may be up to an entire multiline cell, such as
+    /// 'foo = a.isal'
+    pub code: String,
+
+    /// The cursor position within 'code' (in unicode characters) where completion is requested
+    pub cursor_pos: usize,
+}
+
+#[derive(Serialize, Deserialize, Debug, PartialEq, Clone)]
+pub struct CompleteReply {
+    /// The list of all matches to the completion request, such as
+    /// ['a.isalnum', 'a.isalpha'] for the above example.
+    pub matches: Vec<String>,
+
+    /// The range of text that should be replaced by the above matches when a completion is
+    /// accepted. typically cursor_end is the same as cursor_pos in the request.
+    pub cursor_start: usize,
+    pub cursor_end: usize,
+
+    /// Information that frontend plugins might use for extra display information about completions.
+    pub metadata: serde_json::Map<String, serde_json::Value>,
+
+    /// status should be 'ok' unless an exception was raised during the request,
+    /// in which case it should be 'error', along with the usual error message content
+    /// in other messages.
+    pub status: ReplyStatus,
+}
+
+impl CompleteReply {
+    /// A reply with no matches, replacing nothing at the cursor position
+    pub fn empty(cursor_pos: usize) -> Self {
+        Self {
+            matches      : Vec::new(),
+            cursor_start : cursor_pos,
+            cursor_end   : cursor_pos,
+            metadata     : Default::default(),
+            status       : ReplyStatus::Ok,
+        }
+    }
+}
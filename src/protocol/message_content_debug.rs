@@ -0,0 +1,55 @@
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+/// Sent by the frontend on the control channel to drive the debugger. The content is a request of
+/// the [Debug Adapter Protocol](https://microsoft.github.io/debug-adapter-protocol/specification),
+/// e.g. `setBreakpoints`, along with some Jupyter specific commands like `dumpCell` and
+/// `debugInfo`. See [Debug request](https://jupyter-client.readthedocs.io/en/latest/messaging.html#debug-request)
+#[derive(Serialize, Deserialize, Debug, PartialEq, Clone)]
+pub struct DebugRequest {
+    /// Sequence number of the request, used to match the [DebugReply]
+    pub seq: u64,
+    /// Always `"request"`
+    #[serde(rename = "type")]
+    pub message_type: String,
+    /// The command to execute
+    pub command: String,
+    /// Object containing arguments for the command
+    #[serde(default)]
+    pub arguments: Value,
+}
+
+/// The answer to a [DebugRequest]; a response of the Debug Adapter Protocol
+#[derive(Serialize, Deserialize, Debug, PartialEq, Clone)]
+pub struct DebugReply {
+    pub seq: u64,
+    /// Always `"response"`
+    #[serde(rename = "type")]
+    pub message_type: String,
+    /// The `seq` of the [DebugRequest]
+    pub request_seq: u64,
+    /// Whether the request was successful. If not, `message` says why
+    pub success: bool,
+    /// The command of the [DebugRequest]
+    pub command: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub message: Option<String>,
+    /// The result of the command, if any
+    #[serde(default, skip_serializing_if = "Value::is_null")]
+    pub body: Value,
+}
+
+/// Published on iopub when something happens in the debugger which the frontend did not ask for,
+/// e.g. execution `stopped` at a breakpoint. The content is an event of the Debug Adapter Protocol.
+/// See [Debug event](https://jupyter-client.readthedocs.io/en/latest/messaging.html#debug-event)
+#[derive(Serialize, Deserialize, Debug, PartialEq, Clone)]
+pub struct DebugEventPublication {
+    pub seq: u64,
+    /// Always `"event"`
+    #[serde(rename = "type")]
+    pub message_type: String,
+    /// The kind of event, e.g. `stopped`
+    pub event: String,
+    #[serde(default, skip_serializing_if = "Value::is_null")]
+    pub body: Value,
+}
@@ -0,0 +1,56 @@
+//! Rich output which is not the result of an execution; see
+//! [Display Data](https://jupyter-client.readthedocs.io/en/latest/messaging.html#display-data)
+
+use serde::{Deserialize, Serialize};
+
+/// Information which is not persisted to a notebook or other documents; it is only meaningful to
+/// the frontends currently connected to the kernel.
+#[derive(Serialize, Deserialize, Debug, PartialEq, Clone, Default)]
+pub struct Transient {
+    /// Identifies a display so that it can be updated later with an `update_display_data` message.
+    /// Several outputs may share the same id, in which case all of them are updated.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub display_id: Option<String>,
+}
+
+/// This type of message is used to bring back data that should be displayed (text, html, svg,
+/// etc.) in the frontends. This data is published to all frontends. Each message can have multiple
+/// representations of the data; it is up to the frontend to decide which to use and how. A single
+/// message should contain all possible representations of the same information.
+#[derive(Serialize, Deserialize, Debug, PartialEq, Clone)]
+pub struct DisplayDataPublication {
+    /// The data dict contains key/value pairs, where the keys are MIME types and the values are
+    /// the raw data of the representation in that format.
+    pub data: serde_json::Value,
+
+    /// Any metadata that describes the data
+    pub metadata: serde_json::Map<String, serde_json::Value>,
+
+    /// Optional transient data introduced in 5.1. Information not to be persisted to a notebook or
+    /// other documents. Intended to live only during a live kernel session.
+    #[serde(default)]
+    pub transient: Transient,
+}
+
+/// Update an existing display, identified by `transient.display_id`, with new content. Frontends
+/// update every output with that id in place, rather than adding a new output.
+#[derive(Serialize, Deserialize, Debug, PartialEq, Clone)]
+pub struct UpdateDisplayDataPublication {
+    /// The new data, as in [DisplayDataPublication::data]
+    pub data: serde_json::Value,
+
+    /// Any metadata that describes the data
+    pub metadata: serde_json::Map<String, serde_json::Value>,
+
+    /// Must contain the `display_id` of the display to update
+    pub transient: Transient,
+}
+
+/// This message type is used to clear the output that is visible on the frontend.
+#[derive(Serialize, Deserialize, Debug, PartialEq, Clone)]
+pub struct ClearOutputPublication {
+    /// Wait to clear the output until new output is available. Clears the existing output
+    /// immediately before the new output is displayed. Useful for creating simple animations with
+    /// minimal flickering.
+    pub wait: bool,
+}
@@ -2,7 +2,7 @@ use serde::{Serialize, Deserialize};
 
 use super::ReplyStatus;
 
-#[derive(Serialize, Deserialize, Debug, PartialEq)]
+#[derive(Serialize, Deserialize, Debug, PartialEq, Clone)]
 pub struct ErrorReply{
     pub status:ReplyStatus,
     #[serde(rename = "ename")]
@@ -11,4 +11,138 @@ pub struct ErrorReply{
     pub error_message:String,
     #[serde(rename = "traceback")]
     pub stack_trace:Vec<String>,
-}
\ No newline at end of file
+}
+
+/// Describes an error. These fields are included in the content of an `execute_reply` with status
+/// 'error'.
+#[derive(Serialize, Deserialize, Debug, PartialEq, Clone)]
+pub struct ErrorDetails {
+    /// Exception name, as a string
+    #[serde(rename = "ename")]
+    pub error_name: String,
+    /// Exception value, as a string
+    #[serde(rename = "evalue")]
+    pub error_message: String,
+    /// The traceback will contain a list of frames, represented each as a string.
+    #[serde(rename = "traceback")]
+    pub stack_trace: Vec<String>,
+}
+
+/// ANSI escape codes used to colour tracebacks the same way as IPython
+const RED: &str = "\x1b[0;31m";
+const GREEN: &str = "\x1b[0;32m";
+const BOLD_GREEN: &str = "\x1b[1;32m";
+const RESET: &str = "\x1b[0m";
+
+/// The number of lines shown before the line which failed in each frame of a traceback
+const CONTEXT_LINES: usize = 2;
+
+impl ErrorDetails {
+    /// An error with an IPython style traceback, which so far has no frames; see
+    /// [ErrorDetails::with_frame].
+    ///
+    /// ```text
+    /// ---------------------------------------------------------------------------
+    /// ValueError                                Traceback (most recent call last)
+    /// ValueError: invalid literal
+    /// ```
+    pub fn new(error_name: impl Into<String>, error_message: impl Into<String>) -> Self {
+        let error_name = error_name.into();
+        let error_message = error_message.into();
+        let stack_trace = vec![
+            format!(
+                "{RED}{}{RESET}\n{RED}{error_name:<42}{RESET}Traceback (most recent call last)",
+                "-".repeat(75),
+            ),
+            format!("{RED}{error_name}{RESET}: {error_message}"),
+        ];
+        Self {
+            error_name,
+            error_message,
+            stack_trace,
+        }
+    }
+
+    /// Add a frame to the traceback, after any added previously, showing line `line_number`
+    /// (counting from 1) of `code` and the lines just before it. `location` describes where the
+    /// code came from, e.g. `Cell In[3]`.
+    ///
+    /// ```text
+    /// Cell In[3], line 2
+    ///       1 a = 1
+    /// ----> 2 b = int("x")
+    /// ```
+    pub fn with_frame(mut self, location: &str, code: &str, line_number: usize) -> Self {
+        let mut frame = format!("{GREEN}{location}, line {line_number}{RESET}\n");
+        let first = line_number.saturating_sub(CONTEXT_LINES).max(1);
+        for (number, line) in code.lines().enumerate().map(|(index, line)| (index + 1, line)) {
+            if number < first || number > line_number {
+                continue;
+            }
+            if number == line_number {
+                frame.push_str(&format!("{GREEN}----> {number}{RESET} {line}\n"));
+            } else {
+                frame.push_str(&format!("{BOLD_GREEN}{number:>7}{RESET} {line}\n"));
+            }
+        }
+        // frames go between the header and the final `ename: evalue` line
+        let last = self.stack_trace.len().saturating_sub(1);
+        self.stack_trace.insert(last, frame);
+        self
+    }
+}
+
+/// Any error which is not already an [ErrorDetails] is reported as a generic `Error`, with the
+/// chain of causes as its message
+impl From<anyhow::Error> for ErrorDetails {
+    fn from(error: anyhow::Error) -> Self {
+        error
+            .downcast::<ErrorDetails>()
+            .unwrap_or_else(|error| ErrorDetails::new("Error", format!("{error:#}")))
+    }
+}
+
+impl std::fmt::Display for ErrorDetails {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}: {}", self.error_name, self.error_message)
+    }
+}
+
+/// An [ErrorDetails] can be returned as the error from
+/// [Kernel::execute](crate::kernel::Kernel::execute) to control the `ename` and `evalue` sent to
+/// the client.
+impl std::error::Error for ErrorDetails {}
+
+/// When an error occurs during code execution, it is published on iopub as well as being sent in
+/// the `execute_reply`, so that every frontend sees it.
+#[derive(Serialize, Deserialize, Debug, PartialEq, Clone)]
+pub struct ErrorPublication {
+    #[serde(flatten)]
+    pub error: ErrorDetails,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_traceback() {
+        let error = ErrorDetails::new("ValueError", "bad value")
+            .with_frame("Cell In[7]", "a\nb\nc\nd", 4);
+        assert_eq!(error.stack_trace.len(), 3);
+        assert!(error.stack_trace[0].contains("ValueError"));
+        assert_eq!(
+            error.stack_trace[1],
+            "\x1b[0;32mCell In[7], line 4\x1b[0m\n\x1b[1;32m      2\x1b[0m b\n\x1b[1;32m      3\x1b[0m c\n\x1b[0;32m----> 4\x1b[0m d\n"
+        );
+        assert_eq!(error.stack_trace[2], "\x1b[0;31mValueError\x1b[0m: bad value");
+    }
+
+    #[test]
+    fn test_from_anyhow() {
+        let error = ErrorDetails::from(anyhow::Error::from(ErrorDetails::new("KeyError", "x")));
+        assert_eq!(error.error_name, "KeyError");
+        let error = ErrorDetails::from(anyhow::anyhow!("inner").context("outer"));
+        assert_eq!((error.error_name.as_str(), error.error_message.as_str()), ("Error", "outer: inner"));
+    }
+}
@@ -2,7 +2,9 @@ use std::collections::HashMap;
 
 use serde::{Serialize, Deserialize};
 
-#[derive(Serialize, Deserialize, Debug, PartialEq)]
+use super::ErrorDetails;
+
+#[derive(Serialize, Deserialize, Debug, PartialEq, Clone)]
 pub struct ExecuteRequest {
     /// Source code to be executed by the kernel, one or more lines.
     pub code: String,
@@ -12,6 +14,7 @@ pub struct ExecuteRequest {
     /// and will *not*:
     ///   - broadcast output on the IOPUB channel
     ///   - have an execute_result
+    ///
     /// The default is False.
     pub silent: bool,
     /// A boolean flag which, if True, signals the kernel to populate history
@@ -35,7 +38,7 @@ pub struct ExecuteRequest {
     pub stop_on_error: bool,
 }
 
-#[derive(Serialize, Deserialize, Debug, PartialEq)]
+#[derive(Serialize, Deserialize, Debug, PartialEq, Clone)]
 #[serde(rename_all = "lowercase")]
 pub enum ExecuteReplyStatus {
     Ok,
@@ -43,7 +46,7 @@ pub enum ExecuteReplyStatus {
     Aborted,
 }
 
-#[derive(Serialize, Deserialize, Debug, PartialEq)]
+#[derive(Serialize, Deserialize, Debug, PartialEq, Clone)]
 pub struct ExecuteReply {
     pub status: ExecuteReplyStatus,
     /// The global kernel counter that increases by one with each request that
@@ -55,7 +58,7 @@ pub struct ExecuteReply {
     /// that are made with store_history=True. This counter is used to populate the In[n] and Out[n]
     /// prompts. The value of this counter will be returned as the execution_count field of all
     /// execute_reply and execute_input messages.
-    pub execution_count: i32,
+    pub execution_count: usize,
 
     /// present when status is Ok
     /// 
@@ -64,22 +67,67 @@ pub struct ExecuteReply {
     /// The only requirement of each payload dict is that it have a 'source' key,
     /// which is a string classifying the payload (e.g. 'page').
     #[serde(skip_serializing_if = "Option::is_none")]
-    pub payload: Option<Vec<HashMap<String, String>>>,
+    pub payload: Option<Vec<Payload>>,
 
     /// present when status is Ok
     /// 
-    /// Results for the user_expressions.
+    /// Results for the user_expressions, by the same names as in the request.
     #[serde(skip_serializing_if = "Option::is_none")]
-    pub user_expressions: Option<HashMap<String, String>>,
+    pub user_expressions: Option<HashMap<String, UserExpressionResult>>,
+
+    /// present when status is Error
+    #[serde(flatten)]
+    pub error: Option<ErrorDetails>,
+}
+
+/// An action for the frontend to take once execution has finished, sent in the `payload` of an
+/// [ExecuteReply]. Payloads are deprecated in favour of messages on iopub, but these three are
+/// still understood by the common frontends.
+#[derive(Serialize, Deserialize, Debug, PartialEq, Clone)]
+#[serde(tag = "source", rename_all = "snake_case")]
+pub enum Payload {
+    /// Put `text` into the next input cell
+    SetNextInput {
+        text: String,
+        /// Replace the contents of the cell which was executed instead of creating a new one
+        #[serde(default)]
+        replace: bool,
+    },
+    /// Show `data` in the frontend's pager, e.g. help text
+    Page {
+        /// A mime bundle, like the `data` of an execute_result
+        data: serde_json::Value,
+        /// The line to start showing `data` from
+        #[serde(default)]
+        start: usize,
+    },
+    /// Ask the frontend to exit
+    AskExit {
+        /// Leave the kernel running after the frontend exits
+        #[serde(default)]
+        keepkernel: bool,
+    },
 }
 
+/// The result of evaluating one of the `user_expressions` of an [ExecuteRequest]. Each expression
+/// succeeds or fails on its own, regardless of the others and of the code which was executed.
+#[derive(Serialize, Deserialize, Debug, PartialEq, Clone)]
+#[serde(tag = "status", rename_all = "lowercase")]
+pub enum UserExpressionResult {
+    /// The value of the expression as a mime bundle, like the `data` of an execute_result
+    Ok {
+        data: serde_json::Value,
+        metadata: serde_json::Map<String, serde_json::Value>,
+    },
+    Error(ErrorDetails),
+}
 
 /// Results can have multiple simultaneous formats depending on its configuration. A plain text
 /// representation should always be provided in the text/plain mime-type. Frontends are free to
 /// display any or all of these according to its capabilities. Frontends should ignore mime-types
 /// they do not understand. The data itself is any JSON object and depends on the format. It is
 /// often, but not always a string.
-#[derive(Serialize, Deserialize, Debug, PartialEq)]
+#[derive(Serialize, Deserialize, Debug, PartialEq, Clone)]
 pub struct ExecuteResultPublication{
     /// The counter for this execution is also provided so that clients can
     /// display it, since IPython automatically creates variables called _N
@@ -101,7 +149,7 @@ pub struct ExecuteResultPublication{
 
 /// To let all frontends know what code is being executed at any given time, these messages contain
 /// a re-broadcast of the code portion of an execute_request, along with the execution_count.
-#[derive(Serialize, Deserialize, Debug, PartialEq)]
+#[derive(Serialize, Deserialize, Debug, PartialEq, Clone)]
 pub struct ExecuteInputPublication{
     /// Source code to be executed, one or more lines
     pub code:String,
@@ -111,10 +159,49 @@ pub struct ExecuteInputPublication{
 }
 
 
-#[derive(Serialize, Deserialize, Debug, PartialEq)]
+#[derive(Serialize, Deserialize, Debug, PartialEq, Clone)]
 pub struct StreamPublication{
     /// The name of the stream is one of 'stdout', 'stderr'
     pub name:String,
     /// The text is an arbitrary string to be written to that stream
     pub text:String,
-}
\ No newline at end of file
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_user_expression_results() {
+        let ok = UserExpressionResult::Ok {
+            data: serde_json::json!({"text/plain": "1"}),
+            metadata: Default::default(),
+        };
+        assert_eq!(
+            serde_json::to_string(&ok).unwrap(),
+            r#"{"status":"ok","data":{"text/plain":"1"},"metadata":{}}"#
+        );
+        let error = serde_json::to_value(UserExpressionResult::Error(ErrorDetails::new("NameError", "x"))).unwrap();
+        assert_eq!(error["status"], "error");
+        assert_eq!(error["ename"], "NameError");
+        assert_eq!(error["evalue"], "x");
+    }
+
+    #[test]
+    fn test_payloads() {
+        let set_next_input = Payload::SetNextInput { text: "x".into(), replace: true };
+        assert_eq!(
+            serde_json::to_string(&set_next_input).unwrap(),
+            r#"{"source":"set_next_input","text":"x","replace":true}"#
+        );
+        let page = Payload::Page { data: serde_json::json!({"text/plain": "help"}), start: 0 };
+        assert_eq!(
+            serde_json::to_string(&page).unwrap(),
+            r#"{"source":"page","data":{"text/plain":"help"},"start":0}"#
+        );
+        assert_eq!(
+            serde_json::from_str::<Payload>(r#"{"source":"ask_exit"}"#).unwrap(),
+            Payload::AskExit { keepkernel: false }
+        );
+    }
+}
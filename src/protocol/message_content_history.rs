@@ -1,20 +1,9 @@
-/// TODO: For future reference this is the response format that needs to be implemented
-/// 
-/// content = {
-///   # 'ok' if the request succeeded or 'error', with error information as in all other replies.
-///   'status' : 'ok',
-///   # A list of 3 tuples, either:
-///   # (session, line_number, input) or
-///   # (session, line_number, (input, output)),
-///   # depending on whether output was False or True, respectively.
-///   'history' : list,
-/// }
-
-
 use serde::{Serialize, Deserialize};
 
+use super::ReplyStatus;
+
 /// Enumeration of the different types of history access.
-#[derive(Serialize, Deserialize, Debug, PartialEq)]
+#[derive(Serialize, Deserialize, Debug, PartialEq, Clone)]
 #[serde(rename_all = "snake_case")]
 pub enum HistoryAccessType {
     /// Access the 'tail' of the history, i.e., the last few entries.
@@ -28,43 +17,76 @@ pub enum HistoryAccessType {
 }
 
 /// A request for accessing message content history.
-#[derive(Serialize, Deserialize, Debug, PartialEq)]
+#[derive(Serialize, Deserialize, Debug, PartialEq, Clone)]
 pub struct HistoryRequest {
     /// If True, also return output history in the resulting dict.
-    output: bool,
+    pub output: bool,
 
     /// If True, return the raw input history, else the transformed input.
-    raw: bool,
+    pub raw: bool,
 
     /// The type of history access requested: can be 'range', 'tail', or 'search'.
     #[serde(rename="hist_access_type")]
-    history_access_type: HistoryAccessType,
+    pub history_access_type: HistoryAccessType,
 
-    /// Number of history items to access.
+    /// Number of history items to access (only for 'tail' and 'search').
     #[serde(rename="n")]
-    number_of_items: usize,
+    pub number_of_items: Option<usize>,
 
     /// If hist_access_type is 'range', this is the session number.
     /// Session is a number that increments each time the kernel starts; you can specify
     /// a positive session number, or a negative number to count back from the current session.
     #[serde(rename="session")]
-    kernel_session: Option<i32>,
+    pub kernel_session: Option<i32>,
 
-    /// Start line (cell) number within the session (only for 'range').
+    /// Start line (cell) number within the session (only for 'range'). Defaults to 1.
     #[serde(rename="start")]
-    line_start: Option<i32>,
+    pub line_start: Option<i32>,
 
-    /// Stop line (cell) number within the session (only for 'range').
+    /// Stop line (cell) number within the session (only for 'range'). This line is not included.
+    /// Defaults to the end of the session.
     #[serde(rename="stop")]
-    line_stop: Option<i32>,
+    pub line_stop: Option<i32>,
 
     /// If hist_access_type is 'search', this is the glob pattern for matching cells.
     #[serde(rename="pattern")]
-    search_pattern: Option<String>,
+    pub search_pattern: Option<String>,
 
     /// If hist_access_type is 'search' and unique is true, do not include duplicated history.
     /// Default is false.
-    unique: Option<bool>,
+    pub unique: Option<bool>,
+}
+
+/// One cell of history, serialized as a 3 tuple
+#[derive(Serialize, Deserialize, Debug, PartialEq, Clone)]
+#[serde(untagged)]
+pub enum HistoryItem {
+    /// `(session, line_number, input)`
+    Input(u32, usize, String),
+    /// `(session, line_number, (input, output))`. The output is `null` if the cell had no
+    /// result.
+    InputOutput(u32, usize, (String, Option<String>)),
+}
+
+/// Reply to a [HistoryRequest]
+#[derive(Serialize, Deserialize, Debug, PartialEq, Clone)]
+pub struct HistoryReply {
+    /// 'ok' if the request succeeded or 'error', with error information as in all other replies.
+    pub status: ReplyStatus,
+
+    /// A list of 3 tuples, either `(session, line_number, input)` or
+    /// `(session, line_number, (input, output))`, depending on whether output was False or True,
+    /// respectively.
+    pub history: Vec<HistoryItem>,
+}
+
+impl Default for HistoryReply {
+    fn default() -> Self {
+        Self {
+            status  : ReplyStatus::Ok,
+            history : Vec::new(),
+        }
+    }
 }
 
 #[cfg(test)]
@@ -76,4 +98,19 @@ mod tests {
         let history_request: HistoryRequest = serde_json::from_slice(data).unwrap();
         println!("{:?}", history_request);
     }
+
+    #[test]
+    fn test_history_reply(){
+        let reply = HistoryReply {
+            status: ReplyStatus::Ok,
+            history: vec![
+                HistoryItem::Input(1, 1, "a".into()),
+                HistoryItem::InputOutput(1, 2, ("b".into(), None)),
+            ],
+        };
+        assert_eq!(
+            serde_json::to_string(&reply).unwrap(),
+            r#"{"status":"ok","history":[[1,1,"a"],[1,2,["b",null]]]}"#
+        );
+    }
 }
\ No newline at end of file
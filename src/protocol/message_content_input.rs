@@ -0,0 +1,23 @@
+use serde::{Deserialize, Serialize};
+
+/// Sent by the kernel on the stdin channel when the code being executed asks the user for input,
+/// e.g. python's `input()`. The frontend should show the `prompt` and answer with an
+/// [InputReply].
+///
+/// Only sent if the `execute_request` had `allow_stdin` set.
+#[derive(Serialize, Deserialize, Debug, PartialEq, Clone)]
+pub struct InputRequest {
+    /// the text to show at the prompt
+    pub prompt: String,
+
+    /// Is the request for a password?
+    /// If so, the frontend shouldn't echo input.
+    pub password: bool,
+}
+
+/// The frontend's answer to an [InputRequest]
+#[derive(Serialize, Deserialize, Debug, PartialEq, Clone)]
+pub struct InputReply {
+    /// the text entered by the user
+    pub value: String,
+}
@@ -0,0 +1,45 @@
+use serde::{Deserialize, Serialize};
+
+use super::ReplyStatus;
+
+/// Code can be inspected to show useful information to the user. It is up to the Kernel to decide
+/// what information should be displayed, and its formatting.
+#[derive(Serialize, Deserialize, Debug, PartialEq, Clone)]
+pub struct InspectRequest {
+    /// The code context in which introspection is requested
+    /// this may be up to an entire multiline cell.
+    pub code: String,
+
+    /// The cursor position within 'code' (in unicode characters) where inspection is requested
+    pub cursor_pos: usize,
+
+    /// The level of detail desired.  In IPython, the default (0) is equivalent to typing
+    /// 'x?' at the prompt, 1 is equivalent to 'x??'.
+    /// The difference is up to kernels, but in IPython level 1 includes the source code
+    /// if available.
+    pub detail_level: u8,
+}
+
+#[derive(Serialize, Deserialize, Debug, PartialEq, Clone)]
+pub struct InspectReply {
+    /// 'ok' if the request succeeded or 'error', with error information as in all other replies.
+    pub status: ReplyStatus,
+
+    /// found should be true if an object was found, false otherwise
+    pub found: bool,
+
+    /// data can be empty if nothing is found
+    pub data: serde_json::Map<String, serde_json::Value>,
+    pub metadata: serde_json::Map<String, serde_json::Value>,
+}
+
+impl Default for InspectReply {
+    fn default() -> Self {
+        Self {
+            status   : ReplyStatus::Ok,
+            found    : false,
+            data     : Default::default(),
+            metadata : Default::default(),
+        }
+    }
+}
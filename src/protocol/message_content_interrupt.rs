@@ -0,0 +1,17 @@
+use serde::{Deserialize, Serialize};
+
+use super::ReplyStatus;
+
+/// In case a kernel can not catch operating system interrupt signals (e.g. the used runtime
+/// handles signals and does not allow a user program to define a callback), a kernel can choose
+/// to be notified using a message instead. For this to work, the kernels kernelspec must set
+/// `interrupt_mode` to `message`. An interrupt will then result in the following message on the
+/// control channel. This message has no content.
+#[derive(Serialize, Deserialize, Debug, PartialEq, Clone)]
+pub struct InterruptRequest {}
+
+#[derive(Serialize, Deserialize, Debug, PartialEq, Clone)]
+pub struct InterruptReply {
+    /// 'ok' if the request succeeded or 'error', with error information as in all other replies.
+    pub status: ReplyStatus,
+}
@@ -1,12 +1,13 @@
 use serde::{Deserialize, Serialize};
 
 
-#[derive(Serialize, Deserialize, Debug, PartialEq)]
+#[derive(Serialize, Deserialize, Debug, PartialEq, Clone)]
 pub struct IsCompleteRequest {
-    code:String,
+    /// The code entered so far as a multiline string
+    pub code:String,
 }
 
-#[derive(Serialize, Deserialize, Debug, PartialEq, Default)]
+#[derive(Serialize, Deserialize, Debug, PartialEq, Clone, Default)]
 #[serde(rename_all="snake_case")]
 pub enum IsCompleteReplyStatus {
     /// code is ready to be executed
@@ -23,7 +24,7 @@ pub enum IsCompleteReplyStatus {
     Unknown,
 }
 
-#[derive(Serialize, Deserialize, Debug, PartialEq)]
+#[derive(Serialize, Deserialize, Debug, PartialEq, Clone)]
 pub struct IsCompleteReply {
     pub status:IsCompleteReplyStatus,
     
@@ -3,46 +3,51 @@ use serde::{Serialize, Deserialize};
 
 use super::{ReplyStatus, KERNEL_MESSAGING_VERSION};
 
-#[derive(Debug, Serialize, Deserialize, PartialEq)]
+/// Sent by the client to ask for details about the kernel. This message has no content, so it
+/// deserializes from an empty object `{}`
+#[derive(Debug, Serialize, Deserialize, PartialEq, Clone)]
+pub struct KernelInfoRequest {}
+
+#[derive(Debug, Serialize, Deserialize, PartialEq, Clone)]
 pub struct InfoLink {
-    text: String,
-    url: String,
+    pub text: String,
+    pub url: String,
 }
 
-#[derive(Debug, Serialize, Deserialize, PartialEq)]
+#[derive(Debug, Serialize, Deserialize, PartialEq, Clone)]
 pub struct LanguageInfo {
     /// Name of the programming language that the kernel implements.
     /// Kernel included in IPython returns 'python'.
-    name: String,
+    pub name: String,
 
     /// Language version number.
     /// It is Python version number (e.g., '2.7.3') for the kernel
     /// included in IPython.
-    version: String,
+    pub version: String,
 
     /// mimetype for script files in this language (probably just text/plain ?) 
-    mimetype: String,
+    pub mimetype: String,
 
     /// Extension including the dot, e.g. '.py'
-    file_extension: String,
+    pub file_extension: String,
 
     /// pygments lexer, for highlighting
     /// Only needed if it differs from the 'name' field.
     #[serde(skip_serializing_if = "Option::is_none")]
-    pygments_lexer: Option<String>,
+    pub pygments_lexer: Option<String>,
 
     /// Codemirror mode, for highlighting in the notebook.
     /// Only needed if it differs from the 'name' field.
     /// TODO: apparently this should also accept a dict?
     // skip if none
     #[serde(skip_serializing_if = "Option::is_none")]
-    codemirror_mode: Option<String>,
+    pub codemirror_mode: Option<String>,
 
     /// nbconvert exporter, if notebooks written with this kernel should
     /// be exported with something other than the general 'script'
     /// exporter.
     #[serde(skip_serializing_if = "Option::is_none")]
-    nbconvert_exporter: Option<String>,
+    pub nbconvert_exporter: Option<String>,
 }
 
 impl Default for LanguageInfo {
@@ -59,7 +64,7 @@ impl Default for LanguageInfo {
     }
 }
 
-#[derive(Debug, Serialize, Deserialize, PartialEq)]
+#[derive(Debug, Serialize, Deserialize, PartialEq, Clone)]
 pub struct KernelInfoReply {
     /// 'ok' if the request succeeded or 'error',
     /// with error information as in all other replies.
@@ -113,6 +118,13 @@ pub struct KernelInfoReply {
     /// {"text": "IPython Reference", "url": "https://ipython.org/documentation.html"},
     /// {"text": "NumPy Reference", "url": "https://docs.scipy.org/doc/numpy/reference/"}, ...`
     pub help_links: Vec<InfoLink>,
+
+    /// Optional features of the protocol which the kernel supports, e.g. `kernel subshells`.
+    ///
+    /// The [default](Default::default()) lists everything the [server](crate::server::serve)
+    /// implements on behalf of the kernel.
+    #[serde(default)]
+    pub supported_features: Vec<String>,
 }
 
 impl Default for KernelInfoReply {
@@ -129,6 +141,7 @@ impl Default for KernelInfoReply {
                 text: "Nickkerish Repo".to_owned(),
                 url: "https://github.com/thehappycheese/nickkerish".to_owned(),
             }],
+            supported_features     : vec!["kernel subshells".to_owned()],
         }
     }
 }
\ No newline at end of file
@@ -0,0 +1,25 @@
+use serde::{Deserialize, Serialize};
+
+use super::ReplyStatus;
+
+/// The client sends a shutdown request to the kernel, and once it receives the reply message
+/// (which is otherwise empty), it can assume that the kernel has completed shutdown safely. The
+/// request is sent on the control channel.
+///
+/// Upon their own shutdown, client applications will typically execute a last minute sanity check
+/// and forcefully terminate any kernel that is still alive, to avoid leaving stray processes in
+/// the user’s machine.
+#[derive(Serialize, Deserialize, Debug, PartialEq, Clone)]
+pub struct ShutdownRequest {
+    /// False if final shutdown, or True if shutdown precedes a restart
+    pub restart: bool,
+}
+
+#[derive(Serialize, Deserialize, Debug, PartialEq, Clone)]
+pub struct ShutdownReply {
+    /// 'ok' if the request succeeded or 'error', with error information as in all other replies.
+    pub status: ReplyStatus,
+
+    /// same as the request
+    pub restart: bool,
+}
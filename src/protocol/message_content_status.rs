@@ -2,7 +2,7 @@ use serde::{Deserialize, Serialize};
 
 
 /// See docs for [StatusPublication]
-#[derive(Serialize, Deserialize, Debug, Default, PartialEq)]
+#[derive(Serialize, Deserialize, Debug, Default, PartialEq, Clone)]
 #[serde(rename_all="snake_case")]
 pub enum ExecutionState {
     Busy,
@@ -24,7 +24,7 @@ pub enum ExecutionState {
 /// 
 /// The `starting` status is supposed to be sent just once at startup, but I have not seen it used
 /// by ipython or evcxr
-#[derive(Serialize, Deserialize, Debug, Default, PartialEq)]
+#[derive(Serialize, Deserialize, Debug, Default, PartialEq, Clone)]
 pub struct StatusPublication {
     /// When the kernel starts to handle a message, it will enter the 'busy'
     /// state and when it finishes, it will enter the 'idle' state.
@@ -0,0 +1,47 @@
+use serde::{Deserialize, Serialize};
+
+use super::{ErrorDetails, ReplyStatus};
+
+/// Sent on the control channel to create a subshell: a separate queue of shell requests which
+/// runs concurrently with the main shell, e.g. so that widget callbacks can run while a long cell
+/// is executing. Shell requests are sent to the subshell by setting `subshell_id` in their header.
+/// See [Kernel subshells](https://jupyter.org/enhancement-proposals/91-kernel-subshells/kernel-subshells.html)
+#[derive(Serialize, Deserialize, Debug, PartialEq, Clone)]
+pub struct CreateSubshellRequest {}
+
+#[derive(Serialize, Deserialize, Debug, PartialEq, Clone)]
+pub struct CreateSubshellReply {
+    /// 'ok' if the request succeeded or 'error', with error information as in all other replies.
+    pub status: ReplyStatus,
+    /// The id of the new subshell
+    pub subshell_id: String,
+}
+
+/// Sent on the control channel to delete a subshell. Requests already queued in the subshell are
+/// still answered.
+#[derive(Serialize, Deserialize, Debug, PartialEq, Clone)]
+pub struct DeleteSubshellRequest {
+    pub subshell_id: String,
+}
+
+#[derive(Serialize, Deserialize, Debug, PartialEq, Clone)]
+pub struct DeleteSubshellReply {
+    /// 'ok' if the request succeeded or 'error', with error information as in all other replies.
+    pub status: ReplyStatus,
+
+    /// present when status is Error, i.e. there is no such subshell
+    #[serde(flatten)]
+    pub error: Option<ErrorDetails>,
+}
+
+/// Sent on the control channel to list the subshells which exist. This message has no content.
+#[derive(Serialize, Deserialize, Debug, PartialEq, Clone)]
+pub struct ListSubshellRequest {}
+
+#[derive(Serialize, Deserialize, Debug, PartialEq, Clone)]
+pub struct ListSubshellReply {
+    /// 'ok' if the request succeeded or 'error', with error information as in all other replies.
+    pub status: ReplyStatus,
+    /// The ids of every subshell, not including the main shell
+    pub subshell_id: Vec<String>,
+}
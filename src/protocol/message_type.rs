@@ -1,7 +1,7 @@
 use serde::Deserialize;
 use serde::Serialize;
 
-#[derive(Debug, Deserialize, Serialize, Clone)]
+#[derive(Debug, Deserialize, Serialize, Clone, PartialEq)]
 #[serde(rename_all="snake_case")]
 pub enum MessageType {
     // Shell
@@ -12,13 +12,41 @@ pub enum MessageType {
     IsCompleteRequest,
     IsCompleteReply,
     HistoryRequest,
+    HistoryReply,
+    CompleteRequest,
+    CompleteReply,
+    InspectRequest,
+    InspectReply,
 
     CommOpen,
     CommClose,
     CommMsg,
+    CommInfoRequest,
+    CommInfoReply,
+    // Control
+    ShutdownRequest,
+    ShutdownReply,
+    InterruptRequest,
+    InterruptReply,
+    DebugRequest,
+    DebugReply,
+    CreateSubshellRequest,
+    CreateSubshellReply,
+    DeleteSubshellRequest,
+    DeleteSubshellReply,
+    ListSubshellRequest,
+    ListSubshellReply,
+    // Stdin
+    InputRequest,
+    InputReply,
     // IO Pub
     Stream,
     ExecuteResult,
     ExecuteInput,
     Status,
+    DisplayData,
+    UpdateDisplayData,
+    ClearOutput,
+    Error,
+    DebugEvent,
 }
\ No newline at end of file
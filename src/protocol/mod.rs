@@ -1,4 +1,6 @@
+mod channel;
 mod header;
+mod signing;
 mod message_type;
 mod message;
 mod message_reply_status;
@@ -11,20 +13,37 @@ mod message_content_history;
 mod message_content_is_complete;
 mod message_content_execute;
 mod message_content_comm;
+mod message_content_complete;
+mod message_content_inspect;
+mod message_content_shutdown;
+mod message_content_interrupt;
+mod message_content_input;
+mod message_content_display;
+mod message_content_debug;
+mod message_content_subshell;
 
+pub use channel::{Publication, Reply, Request, StdinRequest};
+pub use signing::SigningKey;
 pub use message_reply_status::ReplyStatus;
-pub use message::{MessageBytes, MessageParsed};
+pub use message::{MessageBytes, MessageParsed, ReplyMessage};
 pub use message_type::MessageType;
 pub use header::Header;
 pub use message_content::MessageContent;
 pub use message_content_status::{ExecutionState, StatusPublication};
-pub use message_content_kernel_info::KernelInfoReply;
-pub use message_content_error::ErrorReply;
-pub use message_content_history::{HistoryAccessType, HistoryRequest};
+pub use message_content_kernel_info::{KernelInfoRequest, KernelInfoReply};
+pub use message_content_error::{ErrorReply, ErrorDetails, ErrorPublication};
+pub use message_content_history::{HistoryAccessType, HistoryRequest, HistoryReply, HistoryItem};
 pub use message_content_is_complete::{IsCompleteReply, IsCompleteRequest, IsCompleteReplyStatus};
-pub use message_content_execute::{ExecuteReply, ExecuteRequest, ExecuteReplyStatus, ExecuteResultPublication, ExecuteInputPublication, StreamPublication};
-pub use message_content_comm::{CommOpen, CommClose, CommMsg};
+pub use message_content_execute::{ExecuteReply, ExecuteRequest, ExecuteReplyStatus, ExecuteResultPublication, ExecuteInputPublication, Payload, StreamPublication, UserExpressionResult};
+pub use message_content_comm::{CommOpen, CommClose, CommMsg, CommInfoRequest, CommInfo, CommInfoReply};
+pub use message_content_complete::{CompleteRequest, CompleteReply};
+pub use message_content_inspect::{InspectRequest, InspectReply};
+pub use message_content_shutdown::{ShutdownRequest, ShutdownReply};
+pub use message_content_interrupt::{InterruptRequest, InterruptReply};
+pub use message_content_input::{InputRequest, InputReply};
+pub use message_content_display::{Transient, DisplayDataPublication, UpdateDisplayDataPublication, ClearOutputPublication};
+pub use message_content_debug::{DebugRequest, DebugReply, DebugEventPublication};
+pub use message_content_subshell::{CreateSubshellRequest, CreateSubshellReply, DeleteSubshellRequest, DeleteSubshellReply, ListSubshellRequest, ListSubshellReply};
 
-pub type HmacSha256 = hmac::Hmac<sha2::Sha256>;
 pub const DELIMITER: &[u8] = b"<IDS|MSG>";
-pub const KERNEL_MESSAGING_VERSION:&'static str = "5.3";
\ No newline at end of file
+pub const KERNEL_MESSAGING_VERSION:&str = "5.3";
\ No newline at end of file
@@ -0,0 +1,118 @@
+use crate::connection_information::SignatureScheme;
+
+use anyhow::{Context, Result};
+use bytes::Bytes;
+use hmac::{digest::KeyInit, Hmac, Mac};
+
+/// The shared key and algorithm used to sign every message, and to check the signature of every
+/// message received, taken from the `key` and `signature_scheme` of the connection file.
+///
+/// An empty key disables authentication; messages are sent with an empty signature, and the
+/// signature of received messages is not checked.
+#[derive(Debug, Clone, Default)]
+pub struct SigningKey {
+    scheme: SignatureScheme,
+    key: String,
+}
+
+impl SigningKey {
+    pub fn new(scheme: SignatureScheme, key: &str) -> Self {
+        Self {
+            scheme,
+            key: key.to_owned(),
+        }
+    }
+
+    /// Whether messages are signed at all
+    pub fn is_enabled(&self) -> bool {
+        !self.key.is_empty()
+    }
+
+    /// The hex digest of the HMAC of the concatenated `parts` of a message, or an empty signature
+    /// if authentication is disabled
+    pub fn sign<'a>(&self, parts: impl IntoIterator<Item = &'a [u8]>) -> Result<Bytes> {
+        if !self.is_enabled() {
+            return Ok(Bytes::new());
+        }
+        let key = self.key.as_bytes();
+        let signature = match self.scheme {
+            SignatureScheme::HmacMd5 => sign::<Hmac<md5::Md5>>(key, parts)?,
+            SignatureScheme::HmacSha1 => sign::<Hmac<sha1::Sha1>>(key, parts)?,
+            SignatureScheme::HmacSha256 => sign::<Hmac<sha2::Sha256>>(key, parts)?,
+            SignatureScheme::HmacSha384 => sign::<Hmac<sha2::Sha384>>(key, parts)?,
+            SignatureScheme::HmacSha512 => sign::<Hmac<sha2::Sha512>>(key, parts)?,
+        };
+        Ok(hex::encode(signature).into())
+    }
+
+    /// Check that `signature` is the signature of the concatenated `parts` of a message. Any
+    /// signature is accepted if authentication is disabled.
+    pub fn verify<'a>(&self, signature: &[u8], parts: impl IntoIterator<Item = &'a [u8]>) -> Result<()> {
+        if !self.is_enabled() {
+            return Ok(());
+        }
+        let key = self.key.as_bytes();
+        let signature = hex::decode(signature).context("The signature is not a hex digest")?;
+        match self.scheme {
+            SignatureScheme::HmacMd5 => verify::<Hmac<md5::Md5>>(key, parts, &signature),
+            SignatureScheme::HmacSha1 => verify::<Hmac<sha1::Sha1>>(key, parts, &signature),
+            SignatureScheme::HmacSha256 => verify::<Hmac<sha2::Sha256>>(key, parts, &signature),
+            SignatureScheme::HmacSha384 => verify::<Hmac<sha2::Sha384>>(key, parts, &signature),
+            SignatureScheme::HmacSha512 => verify::<Hmac<sha2::Sha512>>(key, parts, &signature),
+        }
+    }
+}
+
+fn mac<'a, M: Mac + KeyInit>(key: &[u8], parts: impl IntoIterator<Item = &'a [u8]>) -> Result<M> {
+    let mut mac = <M as KeyInit>::new_from_slice(key)?;
+    for part in parts {
+        mac.update(part);
+    }
+    Ok(mac)
+}
+
+fn sign<'a, M: Mac + KeyInit>(key: &[u8], parts: impl IntoIterator<Item = &'a [u8]>) -> Result<Vec<u8>> {
+    Ok(mac::<M>(key, parts)?.finalize().into_bytes().to_vec())
+}
+
+/// Compared in constant time, so that the time taken does not give away the correct signature
+fn verify<'a, M: Mac + KeyInit>(key: &[u8], parts: impl IntoIterator<Item = &'a [u8]>, signature: &[u8]) -> Result<()> {
+    mac::<M>(key, parts)?
+        .verify_slice(signature)
+        .map_err(|_| anyhow::anyhow!("Signature validation failed"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // From RFC 2202 and RFC 4231
+    const PARTS: [&[u8]; 2] = [b"what do ya want ", b"for nothing?"];
+
+    #[test]
+    fn test_signature_schemes() {
+        for (scheme, expected) in [
+            (SignatureScheme::HmacMd5, "750c783e6ab0b503eaa86e310a5db738"),
+            (SignatureScheme::HmacSha1, "effcdf6ae5eb2fa2d27416d5f184df9c259a7c79"),
+            (SignatureScheme::HmacSha256, "5bdcc146bf60754e6a042426089575c75a003f089d2739839dec58b964ec3843"),
+        ] {
+            let key = SigningKey::new(scheme, "Jefe");
+            let signature = key.sign(PARTS).unwrap();
+            assert_eq!(signature, expected);
+            key.verify(&signature, PARTS).unwrap();
+            assert!(key.verify(&signature, [&b"what do ya want"[..]]).is_err());
+        }
+        for scheme in [SignatureScheme::HmacSha384, SignatureScheme::HmacSha512] {
+            let key = SigningKey::new(scheme, "Jefe");
+            key.verify(&key.sign(PARTS).unwrap(), PARTS).unwrap();
+        }
+    }
+
+    #[test]
+    fn test_empty_key_disables_signing() {
+        let key = SigningKey::new(SignatureScheme::HmacSha256, "");
+        assert_eq!(key.sign(PARTS).unwrap(), "");
+        key.verify(b"", PARTS).unwrap();
+        key.verify(b"not even hex", PARTS).unwrap();
+    }
+}
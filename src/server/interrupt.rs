@@ -0,0 +1,79 @@
+use std::{
+    future::Future,
+    sync::{
+        atomic::{AtomicUsize, Ordering},
+        Mutex,
+    },
+};
+use tokio_util::sync::CancellationToken;
+
+/// Allows the executions currently running on the shell and its subshells to be cancelled from
+/// another task, e.g. in response to an `interrupt_request` on the control channel.
+#[derive(Debug, Default)]
+pub struct Interrupt {
+    /// Shared by every future currently passed to [Interrupt::run]; replaced once cancelled
+    current: Mutex<CancellationToken>,
+    running: AtomicUsize,
+}
+
+impl Interrupt {
+    /// Run `future` until it completes, returning `None` if it was interrupted first.
+    ///
+    /// When interrupted, `future` is dropped at whichever `.await` it is currently suspended at.
+    pub async fn run<F: Future>(&self, future: F) -> Option<F::Output> {
+        let token = self.current.lock().unwrap().clone();
+        let _running = Running::new(&self.running);
+        tokio::select! {
+            result = future => Some(result),
+            _ = token.cancelled() => None,
+        }
+    }
+
+    /// Interrupt every future currently passed to [Interrupt::run]. Returns `false` if there was
+    /// nothing to interrupt.
+    pub fn interrupt(&self) -> bool {
+        if self.running.load(Ordering::SeqCst) == 0 {
+            return false;
+        }
+        std::mem::take(&mut *self.current.lock().unwrap()).cancel();
+        true
+    }
+}
+
+/// Counts a future passed to [Interrupt::run] as running until dropped, even if the call to
+/// [Interrupt::run] is itself cancelled
+struct Running<'a>(&'a AtomicUsize);
+
+impl<'a> Running<'a> {
+    fn new(running: &'a AtomicUsize) -> Self {
+        running.fetch_add(1, Ordering::SeqCst);
+        Self(running)
+    }
+}
+
+impl Drop for Running<'_> {
+    fn drop(&mut self) {
+        self.0.fetch_sub(1, Ordering::SeqCst);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::Arc;
+
+    #[tokio::test]
+    async fn test_interrupt_cancels_running_future() {
+        let interrupt = Arc::new(Interrupt::default());
+        assert!(!interrupt.interrupt(), "nothing is running yet");
+        let running = tokio::spawn({
+            let interrupt = interrupt.clone();
+            async move { interrupt.run(std::future::pending::<()>()).await }
+        });
+        while !interrupt.interrupt() {
+            tokio::task::yield_now().await;
+        }
+        assert_eq!(running.await.unwrap(), None);
+        assert_eq!(interrupt.run(async { 1 }).await, Some(1));
+    }
+}
@@ -0,0 +1,146 @@
+use super::Session;
+use crate::{
+    protocol::{
+        ExecutionState,
+        Header,
+        MessageContent,
+        MessageParsed,
+        Publication,
+        StatusPublication,
+    },
+    util::EmptyObjectOr,
+};
+
+use anyhow::Result;
+use bytes::Bytes;
+use clap::ValueEnum;
+use tokio::sync::mpsc;
+use tracing::debug;
+use zeromq::SocketSend;
+
+/// A cheaply cloneable handle used to broadcast messages on the iopub socket.
+///
+/// The [zeromq::PubSocket] itself is owned by a single task (see [run]) so that any part of the
+/// server can publish without needing exclusive access to the socket.
+#[derive(Debug, Clone)]
+pub struct Iopub {
+    session: Session,
+    topics: IopubTopics,
+    sender: mpsc::UnboundedSender<MessageParsed>,
+}
+
+/// How the topic of each message published on iopub is chosen. Clients subscribe to the topics
+/// they want by prefix; most subscribe to `""`, which receives everything, whatever the scheme.
+#[derive(Debug, Default, Clone, Copy, PartialEq, ValueEnum)]
+pub enum IopubTopics {
+    /// The `msg_type`, e.g. `stream` or `status`
+    #[default]
+    MessageType,
+    /// The convention used by ipykernel; `stream.<name>` for streams, `comm-<comm_id>` for comm
+    /// messages, and `kernel.<session id>.<msg_type>` for everything else
+    Ipykernel,
+    /// No topic at all
+    None,
+}
+
+impl IopubTopics {
+    /// The topic to publish `content` under, for the kernel session `session_id`
+    pub fn topic(&self, session_id: &str, content: &MessageContent) -> Option<String> {
+        match self {
+            IopubTopics::MessageType => Some(message_type_name(content)),
+            IopubTopics::Ipykernel => Some(match content {
+                MessageContent::StreamPublication(stream) => format!("stream.{}", stream.name),
+                MessageContent::CommOpen(comm_open) => format!("comm-{}", comm_open.comm_id),
+                MessageContent::CommMsg(comm_msg) => format!("comm-{}", comm_msg.comm_id),
+                MessageContent::CommClose(comm_close) => format!("comm-{}", comm_close.comm_id),
+                _ => format!("kernel.{session_id}.{}", message_type_name(content)),
+            }),
+            IopubTopics::None => None,
+        }
+    }
+}
+
+/// The `msg_type` of `content` as it appears in the header, e.g. `execute_result`
+fn message_type_name(content: &MessageContent) -> String {
+    match serde_json::to_value(content.message_type()) {
+        Ok(serde_json::Value::String(name)) => name,
+        _ => unreachable!("every MessageType serializes as a string"),
+    }
+}
+
+impl Iopub {
+    pub fn new(session: Session, topics: IopubTopics) -> (Self, mpsc::UnboundedReceiver<MessageParsed>) {
+        let (sender, receiver) = mpsc::unbounded_channel();
+        (Self { session, topics, sender }, receiver)
+    }
+
+    pub fn session(&self) -> &Session {
+        &self.session
+    }
+
+    /// Construct and queue a message for sending, under the topic chosen by [IopubTopics]
+    pub fn publish(
+        &self,
+        parent_header: EmptyObjectOr<Header>,
+        content: impl Publication,
+    ) -> Result<()> {
+        self.publish_with_buffers(parent_header, content, Default::default(), Vec::new())
+    }
+
+    /// Like [Iopub::publish], but with metadata and binary buffers attached to the message
+    pub fn publish_with_buffers(
+        &self,
+        parent_header: EmptyObjectOr<Header>,
+        content: impl Publication,
+        metadata: serde_json::Map<String, serde_json::Value>,
+        buffers: Vec<Bytes>,
+    ) -> Result<()> {
+        let mut message = self.session.publication(parent_header, content);
+        message.identities = self.topics
+            .topic(&self.session.id, &message.content)
+            .map(|topic| vec![Bytes::from(topic)])
+            .unwrap_or_default();
+        message.metadata = metadata;
+        message.extra_buffers = buffers;
+        self.sender
+            .send(message)
+            .map_err(|_| anyhow::anyhow!("The iopub socket task has stopped"))
+    }
+
+    /// Publish the execution state of the kernel, see [StatusPublication]
+    pub fn status(&self, parent_header: EmptyObjectOr<Header>, status: ExecutionState) -> Result<()> {
+        self.publish(parent_header, StatusPublication::from(status))
+    }
+}
+
+/// Send everything queued via [Iopub] until every handle has been dropped
+pub async fn run(
+    mut iopub_socket: zeromq::PubSocket,
+    mut receiver: mpsc::UnboundedReceiver<MessageParsed>,
+) -> Result<()> {
+    while let Some(message) = receiver.recv().await {
+        println_debug!("PUBLISH: {message}");
+        iopub_socket.send(message.encode()?.into()).await?;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::protocol::{CommMsg, StatusPublication, StreamPublication};
+
+    #[test]
+    fn test_topics() {
+        let stream: MessageContent = StreamPublication { name: "stdout".into(), text: "".into() }.into();
+        let status: MessageContent = StatusPublication::from(ExecutionState::Idle).into();
+        let comm_msg: MessageContent = CommMsg { comm_id: "c".into(), data: Default::default() }.into();
+
+        assert_eq!(IopubTopics::MessageType.topic("s", &stream).as_deref(), Some("stream"));
+        assert_eq!(IopubTopics::MessageType.topic("s", &status).as_deref(), Some("status"));
+        assert_eq!(IopubTopics::Ipykernel.topic("s", &stream).as_deref(), Some("stream.stdout"));
+        assert_eq!(IopubTopics::Ipykernel.topic("s", &status).as_deref(), Some("kernel.s.status"));
+        assert_eq!(IopubTopics::Ipykernel.topic("s", &comm_msg).as_deref(), Some("comm-c"));
+        assert_eq!(IopubTopics::None.topic("s", &status), None);
+    }
+}
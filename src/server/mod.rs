@@ -0,0 +1,1042 @@
+mod interrupt;
+mod iopub;
+mod session;
+mod stdin;
+mod subshells;
+
+pub use interrupt::Interrupt;
+pub use iopub::{Iopub, IopubTopics};
+pub use session::Session;
+pub use stdin::Stdin;
+
+use subshells::Subshells;
+
+use crate::{
+    comm::Comms,
+    connection_information::ConnectionInformation,
+    debugger::Debugger,
+    history::History,
+    kernel::{ExecutionContext, Kernel},
+    protocol::{
+        MessageBytes,
+        MessageParsed,
+        MessageContent,
+        ReplyMessage,
+        SigningKey,
+        ExecutionState,
+        ExecuteRequest,
+        ExecuteReply,
+        CommInfoReply,
+        ExecuteInputPublication,
+        ExecuteReplyStatus,
+        ReplyStatus,
+        ShutdownReply,
+        InterruptReply,
+        ErrorDetails,
+        ErrorPublication,
+        CreateSubshellReply,
+        DeleteSubshellReply,
+        ListSubshellReply,
+        UserExpressionResult,
+    },
+    util::{iso_8601_Z_now, zmq_message_pretty_print},
+};
+
+use anyhow::Result;
+use std::{
+    future::Future,
+    pin::Pin,
+    sync::{atomic::{AtomicUsize, Ordering}, Arc, Mutex},
+};
+use tokio::sync::mpsc;
+use tokio_util::sync::CancellationToken;
+use tracing::debug;
+use zeromq::{SocketRecv, SocketSend, ZmqMessage};
+
+
+/// Run a jupyter kernel which uses `kernel` to respond to requests.
+///
+/// Binds the sockets described by `connection_information` then handles requests until a
+/// `shutdown_request` is received or an error occurs.
+///
+/// Executed cells are recorded in `history`; pass [History::new] to keep them in memory only, or
+/// [History::open] to save them to disk.
+///
+/// Messages are published on iopub under the topics chosen by `iopub_topics`.
+pub async fn serve<K: Kernel>(
+    connection_information: ConnectionInformation,
+    kernel: K,
+    history: History,
+    iopub_topics: IopubTopics,
+) -> Result<()> {
+    println_debug!("Server Connecting...");
+
+    // Define global constants
+    let session = Session::new(connection_information.signing_key());
+
+    let shell_socket: zeromq::RouterSocket =
+        connection_information.create_socket_shell().await?;
+    //
+    let iopub_socket: zeromq::PubSocket = connection_information.create_socket_iopub().await?;
+    // For kernel to request stdin from frontend
+    let stdin_socket: zeromq::RouterSocket = connection_information.create_socket_stdin().await?;
+    // for shutdown restart and debug requests from client
+    let control_socket: zeromq::RouterSocket =
+        connection_information.create_socket_control().await?;
+    let heartbeat_socket: zeromq::RepSocket =
+        connection_information.create_socket_heartbeat().await?;
+
+    println_debug!("Successfully Created Sockets");
+
+    // Cancelled once a shutdown_request has been answered, which stops every task below
+    let shutdown = CancellationToken::new();
+
+    println_debug!("Starting Heartbeat");
+    let heartbeat_join_handel = tokio::spawn({
+        let shutdown = shutdown.clone();
+        async move {
+            until_shutdown(&shutdown, handel_heartbeat(heartbeat_socket))
+                .await
+                .inspect_err(|err| println_debug!("Heartbeat Error: {:?}", err)).unwrap()
+        }
+    });
+
+    let (iopub, iopub_receiver) = Iopub::new(session.clone(), iopub_topics);
+    let iopub_join_handel = tokio::spawn(async move {
+        iopub::run(iopub_socket, iopub_receiver)
+            .await
+            .inspect_err(|err| println_debug!("Iopub Error: {:?}", err)).unwrap()
+    });
+
+    let (stdin, stdin_receiver) = Stdin::new(session.clone());
+    let stdin_join_handel = tokio::spawn({
+        let shutdown = shutdown.clone();
+        let key = session.key.clone();
+        async move {
+            until_shutdown(&shutdown, stdin::run(stdin_socket, stdin_receiver, key))
+                .await
+                .inspect_err(|err| println_debug!("Stdin Error: {:?}", err)).unwrap()
+        }
+    });
+
+    iopub.status(Default::default(), ExecutionState::Starting)?;
+    iopub.status(Default::default(), ExecutionState::Idle)?;
+
+    let comms = Comms::new(iopub.clone());
+    kernel.register_comm_targets(&comms);
+
+    let debugger = Debugger::new(iopub.clone(), &kernel.kernel_info().language_info.file_extension);
+
+    let (shell_replies, shell_replies_receiver) = mpsc::unbounded_channel();
+
+    // Shared between the shell and control tasks so that control requests can be answered (and
+    // can interrupt) while the shell is busy with a long running execution
+    let shared = Arc::new(Shared {
+        kernel,
+        session,
+        iopub,
+        stdin,
+        comms,
+        debugger,
+        subshells: Default::default(),
+        shell_replies,
+        shutdown: shutdown.clone(),
+        interrupt: Default::default(),
+        history: Mutex::new(history),
+        execution_count: AtomicUsize::new(0),
+        executing: Default::default(),
+        restarts: AtomicUsize::new(0),
+    });
+
+    println_debug!("Starting Main Shell");
+    let (main_shell, main_shell_queue) = mpsc::unbounded_channel();
+    let main_shell_join_handel = tokio::spawn({
+        let shared = shared.clone();
+        async move {
+            until_shutdown(&shared.shutdown, serve_queue(main_shell_queue, shared.clone()))
+                .await
+                .inspect_err(|err| println_debug!("Main Shell Error: {:?}", err)).unwrap()
+        }
+    });
+
+    println_debug!("Starting Control");
+    let control_join_handel = tokio::spawn({
+        let shared = shared.clone();
+        async move {
+            serve_control(control_socket, &shared)
+                .await
+                .inspect_err(|err| println_debug!("Control Error: {:?}", err)).unwrap()
+        }
+    });
+
+    println_debug!("Listening for interrupt signals");
+    let signal_join_handel = tokio::spawn({
+        let shared = shared.clone();
+        async move {
+            until_shutdown(&shared.shutdown, handle_interrupt_signals(&shared))
+                .await
+                .inspect_err(|err| println_debug!("Signal Handler Error: {:?}", err)).unwrap()
+        }
+    });
+
+    serve_shell(shell_socket, shell_replies_receiver, main_shell, &shared).await?;
+
+    println_debug!("Waiting for threads");
+    // The iopub task finishes sending anything still queued once every handle has been dropped
+    shared.comms.close_all();
+    drop(shared);
+    tokio::try_join!(
+        heartbeat_join_handel,
+        main_shell_join_handel,
+        control_join_handel,
+        signal_join_handel,
+        stdin_join_handel,
+        iopub_join_handel,
+    )?;
+    println_debug!("Server Existing Without Error.");
+    Ok(())
+}
+
+/// State shared by the tasks serving the shell and control sockets
+struct Shared<K> {
+    kernel: K,
+    session: Session,
+    iopub: Iopub,
+    stdin: Stdin,
+    comms: Comms,
+    debugger: Debugger,
+    subshells: Subshells,
+    /// Replies to requests made on the shell socket, from the main shell or a subshell
+    shell_replies: mpsc::UnboundedSender<ReplyMessage>,
+    /// Cancelled once a shutdown_request has been answered
+    shutdown: CancellationToken,
+    /// Used to cancel the execution in progress on the shell
+    interrupt: Interrupt,
+    /// Cells executed with `store_history` set
+    history: Mutex<History>,
+    /// The number of cells executed with `store_history` set since the kernel (re)started, used
+    /// for the `In[n]` and `Out[n]` prompts
+    execution_count: AtomicUsize,
+    /// Read locked by each execution until it has been answered, so that a restart can wait for
+    /// the executions it interrupts before resetting the state they use
+    executing: tokio::sync::RwLock<()>,
+    /// The number of times the kernel has restarted, see [Queued]
+    restarts: AtomicUsize,
+}
+
+/// A shell request waiting in the queue of the main shell or a subshell
+struct Queued {
+    message: MessageParsed,
+    /// The value of [Shared::restarts] when the request was received. execute_requests received
+    /// before a restart are aborted rather than run in the new session.
+    restarts: usize,
+}
+
+impl<K: Kernel> Shared<K> {
+    /// Interrupt the current execution, if any
+    fn interrupt(&self) {
+        self.kernel.interrupt();
+        if !self.interrupt.interrupt() {
+            println_debug!("Interrupt received, but nothing is executing");
+        }
+    }
+}
+
+/// Serve the shell socket: queue each request for the subshell named in its header (or for the
+/// main shell if there is none), and send the replies produced by every queue.
+async fn serve_shell<K: Kernel>(
+    mut socket: zeromq::RouterSocket,
+    mut replies: mpsc::UnboundedReceiver<ReplyMessage>,
+    main_shell: mpsc::UnboundedSender<Queued>,
+    shared: &Shared<K>,
+) -> Result<()> {
+    loop {
+        tokio::select! {
+            received = socket.recv() => {
+                let Some(message_received) = decode("SHELL", received?, &shared.session.key) else {
+                    continue;
+                };
+                let queue = match &message_received.header.subshell_id {
+                    None => Some(main_shell.clone()),
+                    Some(subshell_id) => shared.subshells.get(subshell_id),
+                };
+                match queue {
+                    Some(queue) => queue.send(Queued {
+                        message: message_received,
+                        restarts: shared.restarts.load(Ordering::SeqCst),
+                    })?,
+                    None => println_debug!(
+                        "Ignoring request for unknown subshell {:?}",
+                        message_received.header.subshell_id
+                    ),
+                }
+            },
+            Some(reply) = replies.recv() => {
+                socket.send(reply.encode()?.into()).await?;
+            },
+            _ = shared.shutdown.cancelled() => {
+                // e.g. the reply to a shutdown_request sent on the shell
+                while let Ok(reply) = replies.try_recv() {
+                    socket.send(reply.encode()?.into()).await?;
+                }
+                return Ok(());
+            },
+        }
+    }
+}
+
+/// Answer the requests queued for the main shell or one subshell, one at a time, until the queue
+/// is closed.
+///
+/// When an execute_request with `stop_on_error` fails, the execute_requests already waiting in
+/// the queue are aborted rather than run, e.g. the remaining cells of a "Run All". So are those
+/// which were waiting when the kernel restarted.
+///
+/// Boxed since [handle_request] spawns this for each new subshell; without a concrete type the
+/// compiler cannot tell that the recursive future is `Send`.
+fn serve_queue<K: Kernel>(
+    mut queue: mpsc::UnboundedReceiver<Queued>,
+    shared: Arc<Shared<K>>,
+) -> Pin<Box<dyn Future<Output = Result<()>> + Send>> {
+    Box::pin(async move {
+        while let Some(queued) = queue.recv().await {
+            let abort_queued = serve_queued(queued, &shared).await?;
+            if abort_queued {
+                while let Ok(Queued { message: message_received, .. }) = queue.try_recv() {
+                    if let MessageContent::ExecuteRequest(execute_request) = &message_received.content {
+                        abort_request(&message_received, execute_request, &shared)?;
+                    } else {
+                        handle_request("SHELL", message_received, &shared.shell_replies, &shared).await?;
+                    }
+                }
+            }
+        }
+        Ok(())
+    })
+}
+
+/// Handle a request taken from a shell queue, see [serve_queue]
+async fn serve_queued<K: Kernel>(queued: Queued, shared: &Arc<Shared<K>>) -> Result<bool> {
+    let Queued { message: message_received, restarts } = queued;
+    if !matches!(message_received.content, MessageContent::ExecuteRequest(_)) {
+        return handle_request("SHELL", message_received, &shared.shell_replies, shared).await;
+    }
+    let _executing = shared.executing.read().await;
+    match &message_received.content {
+        MessageContent::ExecuteRequest(execute_request) if restarts != shared.restarts.load(Ordering::SeqCst) => {
+            abort_request(&message_received, execute_request, shared)?;
+            Ok(false)
+        },
+        _ => handle_request("SHELL", message_received, &shared.shell_replies, shared).await,
+    }
+}
+
+/// Serve the control socket, answering each request as soon as it arrives, even while the shell
+/// is busy
+async fn serve_control<K: Kernel>(
+    mut socket: zeromq::RouterSocket,
+    shared: &Arc<Shared<K>>,
+) -> Result<()> {
+    let (replies, mut outgoing) = mpsc::unbounded_channel();
+    loop {
+        let received = tokio::select! {
+            received = socket.recv() => received?,
+            _ = shared.shutdown.cancelled() => return Ok(()),
+        };
+        let Some(message_received) = decode("CONTROL", received, &shared.session.key) else {
+            continue;
+        };
+        handle_request("CONTROL", message_received, &replies, shared).await?;
+        // sent before checking for shutdown, so that the shutdown_reply is not lost
+        while let Ok(reply) = outgoing.try_recv() {
+            socket.send(reply.encode()?.into()).await?;
+        }
+    }
+}
+
+/// Reply to a queued execute_request with an `aborted` status, without running it
+fn abort_request<K: Kernel>(
+    message_received: &MessageParsed,
+    execute_request: &ExecuteRequest,
+    shared: &Shared<K>,
+) -> Result<()> {
+    println_debug!("ABORT SHELL:: {message_received}");
+    shared.iopub.status(message_received.header.clone().into(), ExecutionState::Busy)?;
+    let response = shared.session.reply(
+        message_received,
+        execute_request,
+        ExecuteReply {
+            status: ExecuteReplyStatus::Aborted,
+            execution_count: shared.execution_count.load(Ordering::SeqCst),
+            payload: None,
+            user_expressions: None,
+            error: None,
+        },
+    ).with_metadata(execute_reply_metadata(iso_8601_Z_now(), &ExecuteReplyStatus::Aborted));
+    println_debug!("Sending ExecuteReply {response:}");
+    shared.shell_replies.send(response)?;
+    shared.iopub.status(message_received.header.clone().into(), ExecutionState::Idle)?;
+    Ok(())
+}
+
+/// The metadata ipykernel attaches to an execute_reply: when execution `started`, and the `status`
+/// of the reply again
+fn execute_reply_metadata(
+    started: String,
+    status: &ExecuteReplyStatus,
+) -> serde_json::Map<String, serde_json::Value> {
+    serde_json::Map::from_iter([
+        ("started".to_owned(), started.into()),
+        ("status".to_owned(), serde_json::to_value(status).unwrap_or_default()),
+    ])
+}
+
+/// Unpack a message received on `channel_name` and check its signature, logging why if it is
+/// invalid
+fn decode(channel_name: &str, received: ZmqMessage, key: &SigningKey) -> Option<MessageParsed> {
+    let message_received: MessageBytes = match received.clone().try_into(){
+        Ok(message_received) => message_received,
+        Err(err) => {
+            println_debug!("RECV {channel_name}: {:}", zmq_message_pretty_print(received));
+            println_debug!("Unable to unpack received message: {err:?}");
+            return None;
+        }
+    };
+    match message_received.decode(key){
+        Ok(message_received) => Some(message_received),
+        Err(err) => {
+            println_debug!("Unable to decode received message: {err:?}");
+            None
+        }
+    }
+}
+
+/// Respond to a request received on the shell or control socket, sending any replies to
+/// `replies`.
+///
+/// The control channel is "identical to Shell, but operates on a separate socket to avoid queueing
+/// behind execution requests", so requests from both sockets are handled here. `channel_name` is
+/// only used for logging.
+///
+/// Returns `true` if the requests queued behind this one should be aborted, i.e. an
+/// execute_request with `stop_on_error` failed.
+async fn handle_request<K: Kernel>(
+    channel_name: &str,
+    message_received: MessageParsed,
+    replies: &mpsc::UnboundedSender<ReplyMessage>,
+    shared: &Arc<Shared<K>>,
+) -> Result<bool> {
+    let Shared { kernel, session, iopub, stdin, comms, debugger, subshells, shutdown, interrupt, history, execution_count, executing, restarts, .. } = &**shared;
+    println_debug!("RECV {channel_name}:: {message_received}");
+
+    iopub.status(message_received.header.clone().into(), ExecutionState::Busy)?;
+
+    let mut abort_queued = false;
+    match &message_received.content {
+        MessageContent::KernelInfoRequest(kernel_info_request)=>{
+            let response = session.reply(&message_received, kernel_info_request, kernel.kernel_info());
+            println_debug!("Sending KernelInfoReply {response:}");
+            replies.send(response)?;
+        },
+        MessageContent::ExecuteRequest(execute_request)=>{
+            println_debug!("Tried to execute {:?}", execute_request.code);
+            let started = iso_8601_Z_now();
+            // "silent=True forces store_history to be False"
+            let store_history = execute_request.store_history && !execute_request.silent;
+            let execution_count = if store_history {
+                execution_count.fetch_add(1, Ordering::SeqCst) + 1
+            } else {
+                execution_count.load(Ordering::SeqCst)
+            };
+            if !execute_request.silent {
+                iopub.publish(
+                    message_received.header.clone().into(),
+                    ExecuteInputPublication{
+                        code: execute_request.code.clone(),
+                        execution_count,
+                    },
+                )?;
+            }
+            let context = ExecutionContext::new(
+                iopub.clone(),
+                execute_request.allow_stdin.then(|| stdin.clone()),
+                comms.clone(),
+                debugger.clone(),
+                message_received.identities.clone(),
+                message_received.header.clone().into(),
+                execution_count,
+                execute_request.silent,
+            );
+            let result = comms
+                .in_reply_to(
+                    message_received.header.clone().into(),
+                    interrupt.run(kernel.execute(execute_request, &context)),
+                )
+                .await;
+            debugger.execution_finished(message_received.header.subshell_id.as_deref());
+            let error = match result {
+                Some(Ok(())) => None,
+                Some(Err(err)) => {
+                    println_debug!("Execution failed: {err:?}");
+                    Some(ErrorDetails::from(err))
+                },
+                None => {
+                    println_debug!("Execution was interrupted");
+                    Some(ErrorDetails::new("KeyboardInterrupt", "Execution interrupted by the user"))
+                },
+            };
+            let status = match &error {
+                None => ExecuteReplyStatus::Ok,
+                Some(error) => {
+                    if !execute_request.silent {
+                        iopub.publish(
+                            message_received.header.clone().into(),
+                            ErrorPublication {
+                                error: error.clone(),
+                            },
+                        )?;
+                    }
+                    abort_queued = execute_request.stop_on_error;
+                    ExecuteReplyStatus::Error
+                },
+            };
+            if store_history {
+                if let Err(err) = history.lock().unwrap().append(&execute_request.code, context.output()) {
+                    println_debug!("Failed to save history: {err:?}");
+                }
+            }
+            // only evaluated if the code succeeded, as the namespace may be incomplete otherwise
+            let user_expressions = error.is_none().then(|| {
+                execute_request.user_expressions.iter().map(|(name, expression)| {
+                    let result = match kernel.evaluate_user_expression(expression) {
+                        Ok(data) => UserExpressionResult::Ok {
+                            data,
+                            metadata: Default::default(),
+                        },
+                        Err(err) => UserExpressionResult::Error(ErrorDetails::from(err)),
+                    };
+                    (name.clone(), result)
+                }).collect()
+            });
+            let response = session.reply(
+                &message_received,
+                execute_request,
+                ExecuteReply {
+                    status: status.clone(),
+                    execution_count,
+                    payload: error.is_none().then(|| context.take_payloads()),
+                    user_expressions,
+                    error,
+                },
+            ).with_metadata(execute_reply_metadata(started, &status));
+            println_debug!("Sending ExecuteReply {response:}");
+            replies.send(response)?;
+        },
+        MessageContent::IsCompleteRequest(is_complete_request)=>{
+            let response = session.reply(&message_received, is_complete_request, kernel.is_complete(&is_complete_request.code));
+            println_debug!("Sending IsCompleteReply {response}");
+            replies.send(response)?;
+        },
+        MessageContent::CompleteRequest(complete_request)=>{
+            let response = session.reply(&message_received, complete_request, kernel.complete(complete_request));
+            println_debug!("Sending CompleteReply {response}");
+            replies.send(response)?;
+        },
+        MessageContent::InspectRequest(inspect_request)=>{
+            let response = session.reply(&message_received, inspect_request, kernel.inspect(inspect_request));
+            println_debug!("Sending InspectReply {response}");
+            replies.send(response)?;
+        },
+        MessageContent::HistoryRequest(history_request)=>{
+            let response = session.reply(
+                &message_received,
+                history_request,
+                kernel.history(history_request, &history.lock().unwrap()),
+            );
+            println_debug!("Sending HistoryReply {response}");
+            replies.send(response)?;
+        },
+        MessageContent::CommOpen(comm_open)=>{
+            comms.in_reply_to(message_received.header.clone().into(), async {
+                comms.handle_open(comm_open, &message_received.extra_buffers)
+            }).await?;
+        },
+        MessageContent::CommMsg(comm_msg)=>{
+            let result = comms.in_reply_to(message_received.header.clone().into(), async {
+                comms.handle_message(comm_msg, &message_received.extra_buffers)
+            }).await;
+            if let Err(err) = result {
+                println_debug!("Failed to handle comm_msg: {err:?}");
+            }
+        },
+        MessageContent::CommClose(comm_close)=>{
+            let result = comms.in_reply_to(message_received.header.clone().into(), async {
+                comms.handle_close(comm_close)
+            }).await;
+            if let Err(err) = result {
+                println_debug!("Failed to handle comm_close: {err:?}");
+            }
+        },
+        MessageContent::CommInfoRequest(comm_info_request)=>{
+            let response = session.reply(
+                &message_received,
+                comm_info_request,
+                CommInfoReply {
+                    status: ReplyStatus::Ok,
+                    comms: comms.info(comm_info_request.target_name.as_deref()),
+                },
+            );
+            println_debug!("Sending CommInfoReply {response}");
+            replies.send(response)?;
+        },
+        MessageContent::InterruptRequest(interrupt_request)=>{
+            shared.interrupt();
+            let response = session.reply(
+                &message_received,
+                interrupt_request,
+                InterruptReply {
+                    status: ReplyStatus::Ok,
+                },
+            );
+            println_debug!("Sending InterruptReply {response}");
+            replies.send(response)?;
+        },
+        MessageContent::DebugRequest(debug_request)=>{
+            let response = session.reply(
+                &message_received,
+                debug_request,
+                debugger.handle(debug_request, message_received.header.clone().into()),
+            );
+            println_debug!("Sending DebugReply {response}");
+            replies.send(response)?;
+        },
+        MessageContent::CreateSubshellRequest(create_subshell_request)=>{
+            let (subshell_id, queue) = subshells.create();
+            println_debug!("Starting Subshell {subshell_id}");
+            tokio::spawn({
+                let shared = shared.clone();
+                async move {
+                    if let Err(err) = until_shutdown(&shared.shutdown, serve_queue(queue, shared.clone())).await {
+                        println_debug!("Subshell Error: {err:?}");
+                    }
+                }
+            });
+            let response = session.reply(
+                &message_received,
+                create_subshell_request,
+                CreateSubshellReply {
+                    status: ReplyStatus::Ok,
+                    subshell_id,
+                },
+            );
+            println_debug!("Sending CreateSubshellReply {response}");
+            replies.send(response)?;
+        },
+        MessageContent::DeleteSubshellRequest(delete_subshell_request)=>{
+            let error = (!subshells.delete(&delete_subshell_request.subshell_id)).then(|| {
+                println_debug!("Cannot delete unknown subshell {}", delete_subshell_request.subshell_id);
+                ErrorDetails::new(
+                    "ValueError",
+                    format!("Subshell {} does not exist", delete_subshell_request.subshell_id),
+                )
+            });
+            let status = if error.is_none() { ReplyStatus::Ok } else { ReplyStatus::Error };
+            let response = session.reply(
+                &message_received,
+                delete_subshell_request,
+                DeleteSubshellReply { status, error },
+            );
+            println_debug!("Sending DeleteSubshellReply {response}");
+            replies.send(response)?;
+        },
+        MessageContent::ListSubshellRequest(list_subshell_request)=>{
+            let response = session.reply(
+                &message_received,
+                list_subshell_request,
+                ListSubshellReply {
+                    status: ReplyStatus::Ok,
+                    subshell_id: subshells.list(),
+                },
+            );
+            println_debug!("Sending ListSubshellReply {response}");
+            replies.send(response)?;
+        },
+        MessageContent::ShutdownRequest(shutdown_request)=>{
+            // Stop whatever is executing, and wait until it has been answered, so that nothing
+            // from before the restart ends up in the new session
+            let _restarting = if shutdown_request.restart {
+                shared.interrupt();
+                let restarting = executing.write().await;
+                restarts.fetch_add(1, Ordering::SeqCst);
+                Some(restarting)
+            } else {
+                None
+            };
+            kernel.shutdown(shutdown_request.restart);
+            let response = session.reply(
+                &message_received,
+                shutdown_request,
+                ShutdownReply {
+                    status: ReplyStatus::Ok,
+                    restart: shutdown_request.restart,
+                },
+            );
+            println_debug!("Sending ShutdownReply {response}");
+            replies.send(response)?;
+            if shutdown_request.restart {
+                comms.clear();
+                debugger.clear();
+                subshells.clear();
+                execution_count.store(0, Ordering::SeqCst);
+                if let Err(err) = history.lock().unwrap().new_session() {
+                    println_debug!("Failed to start a new history session: {err:?}");
+                }
+                iopub.status(Default::default(), ExecutionState::Starting)?;
+            } else {
+                iopub.status(message_received.header.clone().into(), ExecutionState::Idle)?;
+                shutdown.cancel();
+                return Ok(false);
+            }
+        },
+        unexpected=>{
+            println_debug!("Ignoring unexpected {:?} message on {channel_name}", unexpected.message_type());
+        },
+    }
+    iopub.status(message_received.header.clone().into(), ExecutionState::Idle)?;
+    Ok(abort_queued)
+}
+
+/// Run `future` until it completes, or until `shutdown` is cancelled
+async fn until_shutdown(
+    shutdown: &CancellationToken,
+    future: impl Future<Output = Result<()>>,
+) -> Result<()> {
+    tokio::select! {
+        result = future => result,
+        _ = shutdown.cancelled() => Ok(()),
+    }
+}
+
+/// Treat SIGINT the same as an `interrupt_request`, for clients using the `"signal"`
+/// `interrupt_mode`.
+///
+/// Note that once this handler is installed SIGINT no longer terminates the process.
+#[cfg(unix)]
+async fn handle_interrupt_signals<K: Kernel>(shared: &Shared<K>) -> Result<()> {
+    use tokio::signal::unix::{signal, SignalKind};
+    let mut signals = signal(SignalKind::interrupt())?;
+    while signals.recv().await.is_some() {
+        println_debug!("Received SIGINT");
+        shared.interrupt();
+    }
+    Ok(())
+}
+
+/// There is no SIGINT outside of unix, the nearest equivalent is Ctrl+C
+#[cfg(not(unix))]
+async fn handle_interrupt_signals<K: Kernel>(shared: &Shared<K>) -> Result<()> {
+    loop {
+        tokio::signal::ctrl_c().await?;
+        println_debug!("Received Ctrl+C");
+        shared.interrupt();
+    }
+}
+
+async fn handel_heartbeat(mut heartbeat_socket: zeromq::RepSocket) -> Result<()> {
+    loop {
+        let message = heartbeat_socket.recv().await?;
+        //debug!("Received heartbeat message {message:?}");
+        heartbeat_socket.send(message).await?;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::protocol::{KernelInfoReply, KernelInfoRequest, Payload, ShutdownRequest};
+    use serde_json::json;
+
+    /// Echoes the code of each execution as a stream and a result, and asks for it to be put in
+    /// the next cell with a payload. Fails if the code is `fail`,
+    /// asks for input first if it is `input`, and never finishes (until interrupted) if it is
+    /// `sleep`.
+    #[derive(Default)]
+    struct Echo {
+        executed: Mutex<Vec<String>>,
+    }
+
+    impl Kernel for Echo {
+        fn kernel_info(&self) -> KernelInfoReply {
+            Default::default()
+        }
+
+        async fn execute(&self, request: &ExecuteRequest, context: &ExecutionContext) -> Result<()> {
+            self.executed.lock().unwrap().push(request.code.clone());
+            context.payload(Payload::SetNextInput { text: request.code.clone(), replace: false });
+            match request.code.as_str() {
+                "fail" => anyhow::bail!("failed"),
+                "input" => { context.input("?", false).await?; },
+                "sleep" => std::future::pending().await,
+                _ => {},
+            }
+            context.stream("stdout", &request.code)?;
+            context.execute_result(json!({"text/plain": request.code}))
+        }
+
+        /// The code of each cell executed so far is defined, nothing else is
+        fn evaluate_user_expression(&self, expression: &str) -> Result<serde_json::Value> {
+            if !self.executed.lock().unwrap().iter().any(|code| code == expression) {
+                return Err(ErrorDetails::new("NameError", format!("name '{expression}' is not defined")).into());
+            }
+            Ok(json!({"text/plain": expression}))
+        }
+    }
+
+    /// The state of a server which is not connected to any sockets, along with the receiving ends
+    /// of iopub and the shell replies
+    fn shared() -> (Arc<Shared<Echo>>, mpsc::UnboundedReceiver<MessageParsed>, mpsc::UnboundedReceiver<ReplyMessage>) {
+        let session = Session::new(Default::default());
+        let (iopub, published) = Iopub::new(session.clone(), Default::default());
+        let (stdin, _) = Stdin::new(session.clone());
+        let (shell_replies, replies) = mpsc::unbounded_channel();
+        let shared = Arc::new(Shared {
+            kernel: Echo::default(),
+            session,
+            comms: Comms::new(iopub.clone()),
+            debugger: Debugger::new(iopub.clone(), ".py"),
+            iopub,
+            stdin,
+            subshells: Default::default(),
+            shell_replies,
+            shutdown: Default::default(),
+            interrupt: Default::default(),
+            history: Mutex::new(History::new()),
+            execution_count: AtomicUsize::new(0),
+            executing: Default::default(),
+            restarts: AtomicUsize::new(0),
+        });
+        (shared, published, replies)
+    }
+
+    fn request(session: &Session, content: impl Into<MessageContent>) -> MessageParsed {
+        let content = content.into();
+        MessageParsed::new(
+            session.key.clone(),
+            Vec::new(),
+            session.header(content.message_type()),
+            Default::default(),
+            Default::default(),
+            content,
+            Vec::new(),
+        )
+    }
+
+    fn execute_request(code: &str) -> ExecuteRequest {
+        ExecuteRequest {
+            code: code.into(),
+            silent: false,
+            store_history: true,
+            user_expressions: Default::default(),
+            allow_stdin: false,
+            stop_on_error: true,
+        }
+    }
+
+    fn received(receiver: &mut mpsc::UnboundedReceiver<MessageParsed>) -> Vec<MessageContent> {
+        std::iter::from_fn(|| receiver.try_recv().ok()).map(|message| message.content).collect()
+    }
+
+    fn replied(replies: &mut mpsc::UnboundedReceiver<ReplyMessage>) -> Vec<MessageContent> {
+        std::iter::from_fn(|| replies.try_recv().ok()).map(|reply| reply.content.clone()).collect()
+    }
+
+    /// Handle `execute_request` on the shell, returning its reply and the execution counts of
+    /// every execute_input and execute_result published meanwhile
+    async fn execute(
+        shared: &Arc<Shared<Echo>>,
+        published: &mut mpsc::UnboundedReceiver<MessageParsed>,
+        replies: &mut mpsc::UnboundedReceiver<ReplyMessage>,
+        execute_request: ExecuteRequest,
+    ) -> (ReplyMessage, Vec<usize>) {
+        let request = request(&shared.session, execute_request);
+        handle_request("SHELL", request, &shared.shell_replies, shared).await.unwrap();
+        let published_counts = received(published).into_iter().filter_map(|content| match content {
+            MessageContent::ExecuteInputPublication(input) => Some(input.execution_count),
+            MessageContent::ExecuteResultPublication(result) => Some(result.execution_count),
+            _ => None,
+        }).collect();
+        (replies.try_recv().unwrap(), published_counts)
+    }
+
+    fn execute_reply(reply: &MessageParsed) -> &ExecuteReply {
+        match &reply.content {
+            MessageContent::ExecuteReply(reply) => reply,
+            other => panic!("Expected an execute_reply, not {other:?}"),
+        }
+    }
+
+    fn execution_count(reply: &MessageParsed) -> usize {
+        execute_reply(reply).execution_count
+    }
+
+    #[tokio::test]
+    async fn test_stop_on_error_aborts_queued_executions() {
+        let (shared, _published, mut replies) = shared();
+        let (queue, queued) = mpsc::unbounded_channel();
+        for content in [
+            execute_request("fail").into(),
+            MessageContent::from(execute_request("a")),
+            KernelInfoRequest {}.into(),
+            execute_request("b").into(),
+        ] {
+            queue.send(Queued { message: request(&shared.session, content), restarts: 0 }).unwrap();
+        }
+        drop(queue);
+        serve_queue(queued, shared.clone()).await.unwrap();
+
+        let replies = replied(&mut replies);
+        let statuses: Vec<_> = replies.iter().map(|reply| match reply {
+            MessageContent::ExecuteReply(reply) => Some(reply.status.clone()),
+            _ => None,
+        }).collect();
+        assert_eq!(statuses, [
+            Some(ExecuteReplyStatus::Error),
+            Some(ExecuteReplyStatus::Aborted),
+            None,
+            Some(ExecuteReplyStatus::Aborted),
+        ]);
+        // other requests are still answered
+        assert!(matches!(replies[2], MessageContent::KernelInfoReply(_)));
+        assert_eq!(*shared.kernel.executed.lock().unwrap(), ["fail"]);
+    }
+
+    #[tokio::test]
+    async fn test_execution_count() {
+        let (shared, mut published, mut replies) = shared();
+        let (reply, published_counts) = execute(&shared, &mut published, &mut replies, execute_request("a")).await;
+        assert_eq!((execution_count(&reply), published_counts), (1, vec![1, 1]));
+        assert_eq!(reply.metadata["status"], "ok");
+        assert!(reply.metadata["started"].as_str().is_some_and(|started| started.ends_with('Z')));
+
+        // only executions stored in the history count
+        let (reply, published_counts) = execute(&shared, &mut published, &mut replies, ExecuteRequest { store_history: false, ..execute_request("b") }).await;
+        assert_eq!((execution_count(&reply), published_counts), (1, vec![1, 1]));
+        // silent forces store_history off
+        let (reply, _) = execute(&shared, &mut published, &mut replies, ExecuteRequest { silent: true, ..execute_request("c") }).await;
+        assert_eq!(execution_count(&reply), 1);
+        let (reply, published_counts) = execute(&shared, &mut published, &mut replies, execute_request("fail")).await;
+        assert_eq!((execution_count(&reply), published_counts), (2, vec![2]));
+        assert_eq!(reply.metadata["status"], "error");
+
+        // restarting starts counting again
+        let (control_replies, _control_replies) = mpsc::unbounded_channel();
+        let restart = request(&shared.session, ShutdownRequest { restart: true });
+        handle_request("CONTROL", restart, &control_replies, &shared).await.unwrap();
+        let (reply, published_counts) = execute(&shared, &mut published, &mut replies, execute_request("d")).await;
+        assert_eq!((execution_count(&reply), published_counts), (1, vec![1, 1]));
+    }
+
+    #[tokio::test]
+    async fn test_silent_execution_publishes_nothing() {
+        let (shared, mut published, mut replies) = shared();
+        for code in ["a", "fail"] {
+            let silent = request(&shared.session, ExecuteRequest { silent: true, ..execute_request(code) });
+            handle_request("SHELL", silent, &shared.shell_replies, &shared).await.unwrap();
+            let published = received(&mut published);
+            assert!(
+                published.iter().all(|content| matches!(content, MessageContent::StatusPublication(_))),
+                "{published:?}"
+            );
+            assert!(matches!(replied(&mut replies)[..], [MessageContent::ExecuteReply(_)]));
+        }
+        assert_eq!(*shared.kernel.executed.lock().unwrap(), ["a", "fail"]);
+        assert!(shared.history.lock().unwrap().entries().is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_restart_interrupts_execution() {
+        let (shared, _published, mut replies) = shared();
+        let (queue, queued) = mpsc::unbounded_channel();
+        let main_shell = tokio::spawn(serve_queue(queued, shared.clone()));
+        let send = |code: &str| {
+            // so that only the restart can abort the queued executions
+            let execute_request = ExecuteRequest { stop_on_error: false, ..execute_request(code) };
+            let message = request(&shared.session, execute_request);
+            queue.send(Queued { message, restarts: shared.restarts.load(Ordering::SeqCst) }).unwrap();
+        };
+        send("a");
+        send("sleep");
+        send("b");
+        while !shared.kernel.executed.lock().unwrap().iter().any(|code| code == "sleep") {
+            tokio::task::yield_now().await;
+        }
+
+        let (control_replies, _control_replies) = mpsc::unbounded_channel();
+        let restart = request(&shared.session, ShutdownRequest { restart: true });
+        handle_request("CONTROL", restart, &control_replies, &shared).await.unwrap();
+        send("c");
+        drop(queue);
+        main_shell.await.unwrap().unwrap();
+
+        let replies: Vec<_> = replied(&mut replies).into_iter().map(|reply| match reply {
+            MessageContent::ExecuteReply(reply) => (
+                reply.status,
+                reply.execution_count,
+                reply.error.map(|error| error.error_name),
+            ),
+            other => panic!("Expected an execute_reply, not {other:?}"),
+        }).collect();
+        assert_eq!(replies, [
+            (ExecuteReplyStatus::Ok, 1, None),
+            (ExecuteReplyStatus::Error, 2, Some("KeyboardInterrupt".to_owned())),
+            // queued before the restart
+            (ExecuteReplyStatus::Aborted, 0, None),
+            (ExecuteReplyStatus::Ok, 1, None),
+        ]);
+        assert_eq!(*shared.kernel.executed.lock().unwrap(), ["a", "sleep", "c"]);
+        let history = shared.history.lock().unwrap();
+        let sessions: Vec<_> = history.entries().iter().map(|entry| (entry.session, entry.line)).collect();
+        assert_eq!(sessions, [(1, 1), (1, 2), (2, 1)]);
+    }
+
+    #[tokio::test]
+    async fn test_input_without_allow_stdin() {
+        let (shared, mut published, mut replies) = shared();
+        let (reply, _) = execute(&shared, &mut published, &mut replies, execute_request("input")).await;
+        let reply = execute_reply(&reply);
+        assert_eq!(reply.status, ExecuteReplyStatus::Error);
+        assert_eq!(reply.error.as_ref().unwrap().error_name, "StdinNotImplementedError");
+    }
+
+    #[tokio::test]
+    async fn test_user_expressions() {
+        let (shared, mut published, mut replies) = shared();
+        let user_expressions = std::collections::HashMap::from([
+            ("defined".to_owned(), "a".to_owned()),
+            ("undefined".to_owned(), "b".to_owned()),
+        ]);
+        // evaluated once the cell has run, so its code is already defined
+        let request = ExecuteRequest { user_expressions: user_expressions.clone(), ..execute_request("a") };
+        let (reply, _) = execute(&shared, &mut published, &mut replies, request).await;
+        let results = execute_reply(&reply).user_expressions.clone().unwrap();
+        assert_eq!(results["defined"], UserExpressionResult::Ok {
+            data: json!({"text/plain": "a"}),
+            metadata: Default::default(),
+        });
+        assert!(matches!(&results["undefined"], UserExpressionResult::Error(error) if error.error_name == "NameError"));
+
+        // not evaluated at all if the cell fails
+        let request = ExecuteRequest { user_expressions, ..execute_request("fail") };
+        let (reply, _) = execute(&shared, &mut published, &mut replies, request).await;
+        assert_eq!(execute_reply(&reply).user_expressions, None);
+    }
+
+    #[tokio::test]
+    async fn test_payloads_dropped_on_error() {
+        let (shared, mut published, mut replies) = shared();
+        let (reply, _) = execute(&shared, &mut published, &mut replies, execute_request("a")).await;
+        assert_eq!(execute_reply(&reply).payload, Some(vec![Payload::SetNextInput { text: "a".into(), replace: false }]));
+        let (reply, _) = execute(&shared, &mut published, &mut replies, execute_request("fail")).await;
+        assert_eq!(execute_reply(&reply).payload, None);
+    }
+}
@@ -0,0 +1,111 @@
+use crate::{
+    protocol::{
+        KERNEL_MESSAGING_VERSION,
+        Header,
+        MessageContent,
+        MessageParsed,
+        MessageType,
+        Publication,
+        ReplyMessage,
+        Request,
+        SigningKey,
+        StdinRequest,
+    },
+    util::{iso_8601_Z_now, EmptyObjectOr},
+};
+
+use bytes::Bytes;
+use uuid::Uuid;
+
+/// The details which are stamped onto the header of every message sent by the kernel, along with
+/// the key used to sign them.
+#[derive(Debug, Clone)]
+pub struct Session {
+    /// Random UUID generated once at kernel startup
+    pub id: String,
+    // TODO: the spec isn't clear if the kernel replies should actually contain the "username" field
+    //       or not, and if so, what the value should be when responding?
+    pub username: String,
+    /// The key used to sign messages, see
+    /// [ConnectionInformation::signing_key](crate::connection_information::ConnectionInformation::signing_key)
+    pub key: SigningKey,
+}
+
+impl Session {
+    pub fn new(key: SigningKey) -> Self {
+        Self {
+            id       : Uuid::new_v4().into(),
+            username : "kernel".to_owned(),
+            key,
+        }
+    }
+
+    /// Create a fresh header for a message sent by this kernel
+    pub fn header(&self, message_type: MessageType) -> Header {
+        Header {
+            message_id   : Uuid::new_v4().into(),
+            message_type,
+            date         : iso_8601_Z_now(),
+            session      : self.id.clone(),
+            username     : self.username.clone(),
+            version      : KERNEL_MESSAGING_VERSION.into(),
+            subshell_id  : None,
+        }
+    }
+
+    /// Create a reply to the `request` received in `message`, which will be routed back to the same
+    /// client. The `msg_type` is that of the `content`, which must be the reply to `request`:
+    ///
+    /// ```compile_fail
+    /// # use nikkerish::{protocol::*, server::Session};
+    /// # fn answer(session: &Session, message: &MessageParsed, request: &KernelInfoRequest, reply: ExecuteReply) {
+    /// session.reply(message, request, reply);
+    /// # }
+    /// ```
+    pub fn reply<R: Request>(&self, message: &MessageParsed, _request: &R, content: R::Reply) -> ReplyMessage {
+        let content: MessageContent = content.into();
+        ReplyMessage::new(message, self.header(content.message_type()), content)
+    }
+
+    /// Create a message to be broadcast on iopub. The `msg_type` is that of the `content`.
+    ///
+    /// The message has no topic yet; [Iopub](super::Iopub) adds one according to its
+    /// [IopubTopics](super::IopubTopics).
+    pub fn publication(
+        &self,
+        parent_header: EmptyObjectOr<Header>,
+        content: impl Publication,
+    ) -> MessageParsed {
+        let content: MessageContent = content.into();
+        let message_type = content.message_type();
+        MessageParsed {
+            key           : self.key.clone(),
+            identities    : Vec::new(),
+            header        : self.header(message_type),
+            parent_header,
+            metadata      : Default::default(),
+            content,
+            extra_buffers : Default::default(),
+        }
+    }
+
+    /// Create a request to be sent on stdin to the client with the given `identities`. The
+    /// `msg_type` is that of the `content`.
+    pub fn stdin_request(
+        &self,
+        identities: Vec<Bytes>,
+        parent_header: EmptyObjectOr<Header>,
+        content: impl StdinRequest,
+    ) -> MessageParsed {
+        let content: MessageContent = content.into();
+        MessageParsed {
+            key           : self.key.clone(),
+            identities,
+            header        : self.header(content.message_type()),
+            parent_header,
+            metadata      : Default::default(),
+            content,
+            extra_buffers : Default::default(),
+        }
+    }
+}
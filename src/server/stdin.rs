@@ -0,0 +1,203 @@
+use super::Session;
+use crate::{
+    protocol::{
+        Header,
+        InputRequest,
+        MessageBytes,
+        MessageContent,
+        MessageParsed,
+        SigningKey,
+    },
+    util::EmptyObjectOr,
+};
+
+use anyhow::Result;
+use bytes::Bytes;
+use std::collections::HashMap;
+use tokio::sync::{mpsc, oneshot};
+use tracing::debug;
+use zeromq::{SocketRecv, SocketSend};
+
+/// An `input_request` waiting to be sent, and where to deliver the value of the `input_reply`
+#[derive(Debug)]
+pub struct PendingInput {
+    request: MessageParsed,
+    reply: oneshot::Sender<String>,
+}
+
+/// A cheaply cloneable handle used to ask a client for input on the stdin socket.
+///
+/// Like [Iopub](super::Iopub), the [zeromq::RouterSocket] is owned by a single task (see [run]).
+#[derive(Debug, Clone)]
+pub struct Stdin {
+    session: Session,
+    sender: mpsc::UnboundedSender<PendingInput>,
+}
+
+impl Stdin {
+    pub fn new(session: Session) -> (Self, mpsc::UnboundedReceiver<PendingInput>) {
+        let (sender, receiver) = mpsc::unbounded_channel();
+        (Self { session, sender }, receiver)
+    }
+
+    /// Send an `input_request` to the client with the given `identities`, then wait for its
+    /// `input_reply`.
+    ///
+    /// The stdin socket is a ROUTER, so `identities` must be those of the shell message which
+    /// started the execution; frontends use the same identity for their shell and stdin sockets.
+    pub async fn input(
+        &self,
+        identities: &[Bytes],
+        parent_header: EmptyObjectOr<Header>,
+        request: InputRequest,
+    ) -> Result<String> {
+        let (reply, receiver) = oneshot::channel();
+        let request = self.session.stdin_request(identities.to_vec(), parent_header, request);
+        self.sender
+            .send(PendingInput { request, reply })
+            .map_err(|_| anyhow::anyhow!("The stdin socket task has stopped"))?;
+        receiver
+            .await
+            .map_err(|_| anyhow::anyhow!("The stdin socket task stopped before an input_reply was received"))
+    }
+}
+
+/// The `input_request`s which have been sent but not answered yet, keyed by message id
+#[derive(Debug, Default)]
+struct Waiting(HashMap<String, oneshot::Sender<String>>);
+
+impl Waiting {
+    /// Remember where to deliver the answer to `request`
+    fn insert(&mut self, request: &MessageParsed, reply: oneshot::Sender<String>) {
+        // Forget the requests of interrupted executions, which will never be answered (or whose
+        // answer nobody is listening for)
+        self.0.retain(|_, reply| !reply.is_closed());
+        self.0.insert(request.header.message_id.clone(), reply);
+    }
+
+    /// Deliver the value of the `input_reply` in `message_received` to the request it answers.
+    /// Returns `false` if it does not answer a pending request, or nobody is listening any more.
+    fn answer(&mut self, message_received: MessageParsed) -> bool {
+        let MessageContent::InputReply(input_reply) = message_received.content else {
+            println_debug!("Ignoring unexpected {:?} message on stdin", message_received.content.message_type());
+            return false;
+        };
+        let parent_id = Option::from(message_received.parent_header)
+            .map(|parent: Header| parent.message_id);
+        match parent_id.and_then(|parent_id| self.0.remove(&parent_id)) {
+            // The execution may have been interrupted in the meantime, in which case nobody is
+            // listening for the reply any more
+            Some(reply) => reply.send(input_reply.value).is_ok(),
+            None => {
+                println_debug!("Ignoring input_reply which does not answer a pending input_request");
+                false
+            },
+        }
+    }
+}
+
+/// Send the `input_request`s queued via [Stdin], and deliver each `input_reply` to whichever
+/// request it answers
+pub async fn run(
+    mut stdin_socket: zeromq::RouterSocket,
+    mut receiver: mpsc::UnboundedReceiver<PendingInput>,
+    key: SigningKey,
+) -> Result<()> {
+    let mut waiting = Waiting::default();
+    loop {
+        tokio::select! {
+            pending = receiver.recv() => {
+                let Some(PendingInput { request, reply }) = pending else {
+                    return Ok(());
+                };
+                println_debug!("Sending InputRequest {request}");
+                waiting.insert(&request, reply);
+                stdin_socket.send(request.encode()?.into()).await?;
+            },
+            received = stdin_socket.recv() => {
+                let message_received = match MessageBytes::try_from(received?)
+                    .and_then(|message_bytes| message_bytes.decode(&key))
+                {
+                    Ok(message_received) => message_received,
+                    Err(err) => {
+                        println_debug!("Unable to decode message received on stdin: {err:?}");
+                        continue;
+                    }
+                };
+                println_debug!("RECV STDIN:: {message_received}");
+                waiting.answer(message_received);
+            },
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::protocol::{InputReply, MessageType};
+
+    fn input_reply(session: &Session, parent_header: EmptyObjectOr<Header>, value: &str) -> MessageParsed {
+        let content: MessageContent = InputReply { value: value.into() }.into();
+        MessageParsed::new(
+            session.key.clone(),
+            Vec::new(),
+            session.header(content.message_type()),
+            parent_header,
+            Default::default(),
+            content,
+            Vec::new(),
+        )
+    }
+
+    /// Ask for input, returning the task waiting for the answer and the input_request sent
+    async fn ask(
+        stdin: &Stdin,
+        pending: &mut mpsc::UnboundedReceiver<PendingInput>,
+        waiting: &mut Waiting,
+    ) -> (tokio::task::JoinHandle<Result<String>>, MessageParsed) {
+        let input = tokio::spawn({
+            let stdin = stdin.clone();
+            async move {
+                stdin.input(&[], Default::default(), InputRequest { prompt: "?".into(), password: false }).await
+            }
+        });
+        let PendingInput { request, reply } = pending.recv().await.unwrap();
+        waiting.insert(&request, reply);
+        (input, request)
+    }
+
+    #[tokio::test]
+    async fn test_input_reply_answers_its_request() {
+        let session = Session::new(Default::default());
+        let (stdin, mut pending) = Stdin::new(session.clone());
+        let mut waiting = Waiting::default();
+        let (first, first_request) = ask(&stdin, &mut pending, &mut waiting).await;
+        let (second, second_request) = ask(&stdin, &mut pending, &mut waiting).await;
+
+        assert!(!waiting.answer(input_reply(&session, session.header(MessageType::InputRequest).into(), "stray")));
+        assert!(!waiting.answer(input_reply(&session, Default::default(), "orphan")));
+        assert!(waiting.answer(input_reply(&session, second_request.header.into(), "2")));
+        assert!(waiting.answer(input_reply(&session, first_request.header.into(), "1")));
+        assert_eq!(first.await.unwrap().unwrap(), "1");
+        assert_eq!(second.await.unwrap().unwrap(), "2");
+        assert!(waiting.0.is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_input_reply_to_interrupted_request_is_ignored() {
+        let session = Session::new(Default::default());
+        let (stdin, mut pending) = Stdin::new(session.clone());
+        let mut waiting = Waiting::default();
+        let (interrupted, interrupted_request) = ask(&stdin, &mut pending, &mut waiting).await;
+        interrupted.abort();
+        assert!(interrupted.await.unwrap_err().is_cancelled());
+
+        assert!(!waiting.answer(input_reply(&session, interrupted_request.header.clone().into(), "late")));
+        // nor is the interrupted request kept once another is sent
+        let (interrupted, _) = ask(&stdin, &mut pending, &mut waiting).await;
+        interrupted.abort();
+        let _ = interrupted.await;
+        let (_input, request) = ask(&stdin, &mut pending, &mut waiting).await;
+        assert_eq!(waiting.0.keys().collect::<Vec<_>>(), [&request.header.message_id]);
+    }
+}
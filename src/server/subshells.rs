@@ -0,0 +1,49 @@
+use super::Queued;
+
+use std::{
+    collections::HashMap,
+    sync::Mutex,
+};
+use tokio::sync::mpsc;
+use uuid::Uuid;
+
+/// The queues of shell requests for each subshell the client has created. Each queue is served by
+/// its own task, so requests sent to different subshells (or to the main shell) run concurrently,
+/// while requests sent to the same subshell run in order.
+#[derive(Debug, Default)]
+pub(crate) struct Subshells {
+    queues: Mutex<HashMap<String, mpsc::UnboundedSender<Queued>>>,
+}
+
+impl Subshells {
+    /// Add a subshell, returning its id and the receiving end of its queue
+    pub fn create(&self) -> (String, mpsc::UnboundedReceiver<Queued>) {
+        let (sender, receiver) = mpsc::unbounded_channel();
+        let subshell_id: String = Uuid::new_v4().into();
+        self.queues.lock().unwrap().insert(subshell_id.clone(), sender);
+        (subshell_id, receiver)
+    }
+
+    /// Remove a subshell. Its task stops once it has answered the requests already queued.
+    /// Returns `false` if there is no such subshell.
+    pub fn delete(&self, subshell_id: &str) -> bool {
+        self.queues.lock().unwrap().remove(subshell_id).is_some()
+    }
+
+    /// The ids of every subshell
+    pub fn list(&self) -> Vec<String> {
+        let mut subshell_ids: Vec<String> = self.queues.lock().unwrap().keys().cloned().collect();
+        subshell_ids.sort();
+        subshell_ids
+    }
+
+    /// The queue of a subshell, if it exists
+    pub fn get(&self, subshell_id: &str) -> Option<mpsc::UnboundedSender<Queued>> {
+        self.queues.lock().unwrap().get(subshell_id).cloned()
+    }
+
+    /// Delete every subshell, e.g. when the kernel restarts
+    pub fn clear(&self) {
+        self.queues.lock().unwrap().clear();
+    }
+}
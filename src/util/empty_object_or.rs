@@ -35,11 +35,11 @@ impl<T> From<T> for EmptyObjectOr<T> {
     }
 }
 
-impl<T> Into<Option<T>> for EmptyObjectOr<T> {
-    fn into(self) -> Option<T> {
-        match self {
-            Self::EmptyObject {} => None,
-            Self::Object(t) => Some(t),
+impl<T> From<EmptyObjectOr<T>> for Option<T> {
+    fn from(value: EmptyObjectOr<T>) -> Self {
+        match value {
+            EmptyObjectOr::EmptyObject {} => None,
+            EmptyObjectOr::Object(t) => Some(t),
         }
     }
 }
@@ -101,6 +101,7 @@ mod tests {
             session: "c".into(),
             date: "d".into(),
             version: "e".into(),
+            subshell_id: None,
         });
         let json = serde_json::to_string(&object_or).unwrap();
         assert_eq!(
@@ -121,6 +122,7 @@ mod tests {
             session: "c".into(),
             date: "d".into(),
             version: "e".into(),
+            subshell_id: None,
         });
         let json = object_or.try_to_json_string().unwrap();
         assert_eq!(
@@ -0,0 +1,21 @@
+use std::{env, path::PathBuf};
+
+/// The user level Jupyter data directory, following the same rules as `jupyter --data-dir`.
+///
+/// See [Data files](https://docs.jupyter.org/en/latest/use/jupyter-directories.html#data-files)
+pub fn jupyter_data_dir() -> Option<PathBuf> {
+    if let Some(data_dir) = env::var_os("JUPYTER_DATA_DIR") {
+        return Some(PathBuf::from(data_dir));
+    }
+    if cfg!(windows) {
+        env::var_os("APPDATA").map(|app_data| PathBuf::from(app_data).join("jupyter"))
+    } else if cfg!(target_os = "macos") {
+        env::var_os("HOME").map(|home| PathBuf::from(home).join("Library").join("Jupyter"))
+    } else {
+        env::var_os("XDG_DATA_HOME")
+            .filter(|data_home| !data_home.is_empty())
+            .map(PathBuf::from)
+            .or_else(|| env::var_os("HOME").map(|home| PathBuf::from(home).join(".local").join("share")))
+            .map(|data_home| data_home.join("jupyter"))
+    }
+}
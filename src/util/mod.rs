@@ -1,4 +1,5 @@
 mod empty_object_or;
+mod jupyter_paths;
 mod to_json_string_bytes;
 mod time;
 mod zmq_message_pretty_print;
@@ -6,6 +7,7 @@ mod zmq_message_pretty_print;
 pub use zmq_message_pretty_print::zmq_message_pretty_print;
 pub use time::iso_8601_Z_now;
 pub use empty_object_or::EmptyObjectOr;
+pub use jupyter_paths::jupyter_data_dir;
 pub use to_json_string_bytes::{
     TryFromJsonBytesString,
     TryToJsonBytesString
@@ -7,7 +7,7 @@ pub trait TryToJsonBytesString {
     fn try_to_json_string(&self) -> Result<String>;
 }
 
-impl<'de, T> TryToJsonBytesString for T
+impl<T> TryToJsonBytesString for T
 where
     T: Serialize,
 {
@@ -0,0 +1,625 @@
+//! Interactive widgets, compatible with the `@jupyter-widgets` frontend packages (ipywidgets 8).
+//!
+//! Each widget is a model whose state is kept in sync between the kernel and the frontend over a
+//! comm with the target `jupyter.widget`, following the
+//! [widget messaging protocol](https://github.com/jupyter-widgets/ipywidgets/blob/main/packages/schema/messages.md)
+//! version 2.1.0.
+//!
+//! Call [Widgets::register] from [Kernel::register_comm_targets](crate::kernel::Kernel::register_comm_targets),
+//! create widgets with [Widgets::create] during an execution, then show them by passing
+//! [Widget::mime_bundle] to [ExecutionContext::display_data](crate::kernel::ExecutionContext::display_data).
+
+pub mod models;
+
+pub use models::WidgetModel;
+
+use crate::comm::{unzip_buffer_paths, zip_buffer_paths, BufferPath, Comm, CommHandler, Comms};
+use models::{
+    Layout,
+    Output,
+    VBox,
+    BASE_MODULE,
+    BASE_MODULE_VERSION,
+    CONTROLS_MODULE,
+    CONTROLS_MODULE_VERSION,
+};
+
+use anyhow::{Context, Result};
+use bytes::Bytes;
+use serde::{de::DeserializeOwned, Serialize};
+use serde_json::{json, Map, Value};
+use std::{
+    collections::HashMap,
+    sync::{Arc, Mutex, OnceLock, Weak},
+};
+use tracing::debug;
+
+/// The version of the widget messaging protocol, sent in the metadata of every `comm_open`
+pub const PROTOCOL_VERSION: &str = "2.1.0";
+/// The comm target of each widget
+pub const WIDGET_TARGET: &str = "jupyter.widget";
+/// The comm target the frontend uses to ask for the state of every widget at once
+pub const CONTROL_TARGET: &str = "jupyter.widget.control";
+
+/// The mime type frontends use to display a widget
+const WIDGET_VIEW_MIME_TYPE: &str = "application/vnd.jupyter.widget-view+json";
+
+type Observer = Arc<dyn Fn(&Widget, &Map<String, Value>) -> Result<()> + Send + Sync>;
+type CustomHandler = Arc<dyn Fn(&Widget, &Value, &[Bytes]) -> Result<()> + Send + Sync>;
+
+struct WidgetInner {
+    /// Set as soon as the comm has been opened
+    comm: OnceLock<Comm>,
+    state: Mutex<Map<String, Value>>,
+    /// The binary parts of the state, which can not be stored in `state`
+    buffers: Mutex<Vec<(BufferPath, Bytes)>>,
+    observers: Mutex<Vec<Observer>>,
+    custom_handlers: Mutex<Vec<CustomHandler>>,
+}
+
+/// A handle to a widget. Cheap to clone; all clones refer to the same widget.
+#[derive(Clone)]
+pub struct Widget {
+    inner: Arc<WidgetInner>,
+}
+
+impl std::fmt::Debug for Widget {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("Widget")
+            .field("id", &self.id())
+            .field("state", &self.inner.state.lock().unwrap())
+            .finish()
+    }
+}
+
+impl Widget {
+    fn new(state: Map<String, Value>, buffers: Vec<(BufferPath, Bytes)>) -> Self {
+        Self {
+            inner: Arc::new(WidgetInner {
+                comm: OnceLock::new(),
+                state: Mutex::new(state),
+                buffers: Mutex::new(buffers),
+                observers: Default::default(),
+                custom_handlers: Default::default(),
+            }),
+        }
+    }
+
+    fn comm(&self) -> &Comm {
+        self.inner.comm.get().expect("The comm of a widget is set when it is created")
+    }
+
+    /// The model id of the widget, which is the id of its comm
+    pub fn id(&self) -> &str {
+        self.comm().id()
+    }
+
+    /// How other widgets refer to this one in their state, e.g. in the children of a box
+    pub fn reference(&self) -> String {
+        format!("IPY_MODEL_{}", self.id())
+    }
+
+    /// The mime bundle which displays this widget
+    pub fn mime_bundle(&self) -> Value {
+        let model_name = self.get("_model_name").unwrap_or_default();
+        json!({
+            "text/plain": format!("{}(model_id={:?})", model_name.as_str().unwrap_or("Widget"), self.id()),
+            WIDGET_VIEW_MIME_TYPE: {
+                "model_id": self.id(),
+                "version_major": 2,
+                "version_minor": 0,
+            },
+        })
+    }
+
+    /// A copy of the whole state of the widget, not including binary buffers
+    pub fn state(&self) -> Map<String, Value> {
+        self.inner.state.lock().unwrap().clone()
+    }
+
+    /// The value of one attribute of the state
+    pub fn get(&self, key: &str) -> Option<Value> {
+        self.inner.state.lock().unwrap().get(key).cloned()
+    }
+
+    /// The state of the widget as one of the [models]
+    pub fn model<M: WidgetModel + DeserializeOwned>(&self) -> Result<M> {
+        serde_json::from_value(Value::Object(self.state()))
+            .with_context(|| format!("The state of widget {} does not match {}", self.id(), M::MODEL_NAME))
+    }
+
+    /// The value the user has chosen: the `value` attribute, or for widgets like [models::Dropdown]
+    /// the label of the selected option
+    pub fn value(&self) -> Option<Value> {
+        let state = self.inner.state.lock().unwrap();
+        if let Some(value) = state.get("value") {
+            return Some(value.clone());
+        }
+        let index = state.get("index")?.as_u64()? as usize;
+        state.get("_options_labels")?.get(index).cloned()
+    }
+
+    /// The binary buffers in the state of the widget, along with where they belong
+    pub fn buffers(&self) -> Vec<(BufferPath, Bytes)> {
+        self.inner.buffers.lock().unwrap().clone()
+    }
+
+    /// Change one attribute of the state, and send the change to the frontend.
+    ///
+    /// Observers are only called for changes made by the frontend, not for changes made here.
+    pub fn set(&self, key: &str, value: impl Serialize) -> Result<()> {
+        let mut state = Map::new();
+        state.insert(key.to_owned(), serde_json::to_value(value)?);
+        self.set_state(state)
+    }
+
+    /// Change several attributes of the state at once, and send the changes to the frontend
+    pub fn set_state(&self, state: Map<String, Value>) -> Result<()> {
+        self.inner.state.lock().unwrap().extend(state.clone());
+        self.comm().send_with_buffer_paths(json!({"method": "update", "state": state}), &[])
+    }
+
+    /// Set a binary attribute at the top level of the state, and send it to the frontend
+    pub fn set_buffer(&self, key: &str, buffer: Bytes) -> Result<()> {
+        let path = vec![Value::from(key)];
+        set_buffer(&mut self.inner.buffers.lock().unwrap(), path.clone(), buffer.clone());
+        self.comm().send_with_buffer_paths(
+            json!({"method": "update", "state": {}}),
+            &[(path, buffer)],
+        )
+    }
+
+    /// Call `observer` whenever the frontend changes the state of the widget, with the attributes
+    /// which changed
+    pub fn observe(&self, observer: impl Fn(&Widget, &Map<String, Value>) -> Result<()> + Send + Sync + 'static) {
+        self.inner.observers.lock().unwrap().push(Arc::new(observer));
+    }
+
+    /// Call `handler` with the `content` and buffers of every custom message sent by the frontend
+    pub fn on_custom_message(&self, handler: impl Fn(&Widget, &Value, &[Bytes]) -> Result<()> + Send + Sync + 'static) {
+        self.inner.custom_handlers.lock().unwrap().push(Arc::new(handler));
+    }
+
+    /// Call `handler` whenever a [models::Button] is clicked
+    pub fn on_click(&self, handler: impl Fn(&Widget) -> Result<()> + Send + Sync + 'static) {
+        self.on_custom_message(move |widget, content, _| {
+            match content.get("event").and_then(Value::as_str) {
+                Some("click") => handler(widget),
+                _ => Ok(()),
+            }
+        });
+    }
+
+    /// Send a custom message to the frontend side of the widget
+    pub fn send_custom(&self, content: Value, buffers: Vec<Bytes>) -> Result<()> {
+        self.comm().send_with_buffers(json!({"method": "custom", "content": content}), buffers)
+    }
+
+    /// Add an output, in the format used by notebook cells, to an [Output] widget; e.g.
+    /// `{"output_type": "stream", "name": "stdout", "text": "hello\n"}`
+    pub fn append_output(&self, output: Value) -> Result<()> {
+        let mut outputs = self.get("outputs").unwrap_or_else(|| json!([]));
+        outputs.as_array_mut().context("outputs is not a list")?.push(output);
+        self.set("outputs", outputs)
+    }
+
+    /// Remove every output from an [Output] widget
+    pub fn clear_output(&self) -> Result<()> {
+        self.set("outputs", json!([]))
+    }
+
+    /// Close the comm of the widget; the frontend will no longer show it
+    pub fn close(&self) -> Result<()> {
+        self.comm().close(Value::Null)
+    }
+
+    /// A message which sends the whole state of the widget to the frontend
+    fn full_state(&self) -> (Value, Value, Vec<Bytes>) {
+        let (buffer_paths, buffers) = unzip_buffer_paths(&self.inner.buffers.lock().unwrap());
+        (Value::Object(self.state()), buffer_paths, buffers)
+    }
+
+    /// Apply a change of state made by the frontend
+    fn update(&self, state: &Map<String, Value>, buffers: Vec<(BufferPath, Bytes)>) {
+        self.inner.state.lock().unwrap().extend(state.clone());
+        let mut own_buffers = self.inner.buffers.lock().unwrap();
+        for (path, buffer) in buffers {
+            set_buffer(&mut own_buffers, path, buffer);
+        }
+    }
+}
+
+fn set_buffer(buffers: &mut Vec<(BufferPath, Bytes)>, path: BufferPath, buffer: Bytes) {
+    match buffers.iter_mut().find(|(existing, _)| *existing == path) {
+        Some(entry) => entry.1 = buffer,
+        None => buffers.push((path, buffer)),
+    }
+}
+
+/// Handles the messages the frontend sends on the comm of one widget
+struct WidgetHandler {
+    widget: Widget,
+    widgets: Widgets,
+}
+
+impl CommHandler for WidgetHandler {
+    fn on_message(&self, comm: &Comm, data: &Map<String, Value>, buffers: &[Bytes]) -> Result<()> {
+        let widget = &self.widget;
+        match data.get("method").and_then(Value::as_str) {
+            Some("update") => {
+                let state = match data.get("state") {
+                    Some(Value::Object(state)) => state.clone(),
+                    _ => anyhow::bail!("The update message has no state"),
+                };
+                let buffer_paths = data.get("buffer_paths");
+                widget.update(&state, zip_buffer_paths(buffer_paths, buffers)?);
+                // Let every frontend, including the one which made the change, know that the
+                // kernel has accepted it
+                comm.send_with_buffers(
+                    json!({
+                        "method": "echo_update",
+                        "state": state,
+                        "buffer_paths": buffer_paths.cloned().unwrap_or_else(|| json!([])),
+                    }),
+                    buffers.to_vec(),
+                )?;
+                let observers = widget.inner.observers.lock().unwrap().clone();
+                for observer in observers {
+                    observer(widget, &state)?;
+                }
+                Ok(())
+            },
+            Some("request_state") => {
+                comm.send_with_buffer_paths(
+                    json!({"method": "update", "state": widget.state()}),
+                    &widget.buffers(),
+                )
+            },
+            Some("custom") => {
+                let content = data.get("content").cloned().unwrap_or_default();
+                let handlers = widget.inner.custom_handlers.lock().unwrap().clone();
+                for handler in handlers {
+                    handler(widget, &content, buffers)?;
+                }
+                Ok(())
+            },
+            method => {
+                println_debug!("Ignoring widget message with unknown method {method:?}");
+                Ok(())
+            },
+        }
+    }
+
+    fn on_close(&self, comm: &Comm, _data: &Map<String, Value>) -> Result<()> {
+        self.widgets.widgets.lock().unwrap().remove(comm.id());
+        Ok(())
+    }
+}
+
+/// Answers the frontend's requests on the `jupyter.widget.control` comm
+struct ControlHandler {
+    widgets: Widgets,
+}
+
+impl CommHandler for ControlHandler {
+    fn on_message(&self, comm: &Comm, data: &Map<String, Value>, _buffers: &[Bytes]) -> Result<()> {
+        match data.get("method").and_then(Value::as_str) {
+            Some("request_states") => {
+                let mut states = Map::new();
+                let mut buffers = Vec::new();
+                for widget in self.widgets.all() {
+                    let state = widget.state();
+                    let mut model = Map::new();
+                    for key in ["model_name", "model_module", "model_module_version"] {
+                        model.insert(key.into(), state.get(&format!("_{key}")).cloned().unwrap_or_default());
+                    }
+                    model.insert("state".into(), state.into());
+                    states.insert(widget.id().to_owned(), model.into());
+                    // the paths are from the top of the message, rather than of each state
+                    for (path, buffer) in widget.buffers() {
+                        let prefix = [widget.id().into(), "state".into()];
+                        buffers.push((prefix.into_iter().chain(path).collect(), buffer));
+                    }
+                }
+                let (buffer_paths, buffers) = unzip_buffer_paths(&buffers);
+                comm.send_with_buffers(
+                    json!({
+                        "method": "update_states",
+                        "states": states,
+                        "buffer_paths": buffer_paths,
+                    }),
+                    buffers,
+                )
+            },
+            method => {
+                println_debug!("Ignoring widget control message with unknown method {method:?}");
+                Ok(())
+            },
+        }
+    }
+}
+
+/// The registry of every widget which is alive. Cheap to clone; all clones share the same state.
+#[derive(Clone)]
+pub struct Widgets {
+    comms: Comms,
+    widgets: Arc<Mutex<HashMap<String, Widget>>>,
+}
+
+impl std::fmt::Debug for Widgets {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("Widgets")
+            .field("widgets", &self.widgets.lock().unwrap().keys().collect::<Vec<_>>())
+            .finish()
+    }
+}
+
+impl Widgets {
+    /// Register the `jupyter.widget` and `jupyter.widget.control` comm targets
+    pub fn register(comms: &Comms) -> Self {
+        let widgets = Self {
+            comms: comms.clone(),
+            widgets: Default::default(),
+        };
+        comms.register_target(WIDGET_TARGET, {
+            let widgets = widgets.clone();
+            move |comm: &Comm, data: &Value, buffers: &[Bytes]| -> Result<Arc<dyn CommHandler>> {
+                // a widget created by the frontend
+                let state = match data.get("state") {
+                    Some(Value::Object(state)) => state.clone(),
+                    _ => anyhow::bail!("The comm_open of a widget has no state"),
+                };
+                let buffers = zip_buffer_paths(data.get("buffer_paths"), buffers)?;
+                let widget = Widget::new(state, buffers);
+                let _ = widget.inner.comm.set(comm.clone());
+                widgets.widgets.lock().unwrap().insert(comm.id().to_owned(), widget.clone());
+                Ok(Arc::new(WidgetHandler { widget, widgets: widgets.clone() }))
+            }
+        });
+        comms.register_target(CONTROL_TARGET, {
+            let widgets = widgets.clone();
+            move |_: &Comm, _: &Value, _: &[Bytes]| -> Result<Arc<dyn CommHandler>> {
+                Ok(Arc::new(ControlHandler { widgets: widgets.clone() }))
+            }
+        });
+        widgets
+    }
+
+    /// Create a widget, along with its layout and style widgets, and open its comm
+    pub fn create<M: WidgetModel>(&self, model: &M) -> Result<Widget> {
+        let mut state = Map::new();
+        state.insert("_model_name".into(), M::MODEL_NAME.into());
+        state.insert("_model_module".into(), M::MODEL_MODULE.into());
+        state.insert("_model_module_version".into(), M::MODEL_MODULE_VERSION.into());
+        state.insert("_view_name".into(), M::VIEW_NAME.into());
+        state.insert("_view_module".into(), M::VIEW_MODULE.into());
+        state.insert("_view_module_version".into(), M::VIEW_MODULE_VERSION.into());
+        state.insert("_view_count".into(), Value::Null);
+        if M::HAS_LAYOUT {
+            state.insert("layout".into(), self.create(&Layout::default())?.reference().into());
+        }
+        if let Some(style_model_name) = M::STYLE_MODEL_NAME {
+            let style = self.create_from_state(
+                json!({
+                    "_model_name": style_model_name,
+                    "_model_module": CONTROLS_MODULE,
+                    "_model_module_version": CONTROLS_MODULE_VERSION,
+                    "_view_name": "StyleView",
+                    "_view_module": BASE_MODULE,
+                    "_view_module_version": BASE_MODULE_VERSION,
+                    "_view_count": null,
+                }).as_object().cloned().unwrap_or_default(),
+                Vec::new(),
+            )?;
+            state.insert("style".into(), style.reference().into());
+        }
+        match serde_json::to_value(model)? {
+            Value::Object(model_state) => state.extend(model_state),
+            _ => anyhow::bail!("The state of {} must serialize to an object", M::MODEL_NAME),
+        }
+        self.create_from_state(state, Vec::new())
+    }
+
+    /// Create a widget with the given state, which must include the `_model_*` and `_view_*`
+    /// attributes which tell the frontend what kind of widget it is
+    pub fn create_from_state(&self, state: Map<String, Value>, buffers: Vec<(BufferPath, Bytes)>) -> Result<Widget> {
+        let widget = Widget::new(state, buffers);
+        let (state, buffer_paths, buffers) = widget.full_state();
+        let mut metadata = Map::new();
+        metadata.insert("version".into(), PROTOCOL_VERSION.into());
+        let comm = self.comms.open_with(
+            WIDGET_TARGET,
+            json!({"state": state, "buffer_paths": buffer_paths}),
+            metadata,
+            buffers,
+            WidgetHandler { widget: widget.clone(), widgets: self.clone() },
+        )?;
+        let _ = widget.inner.comm.set(comm);
+        self.widgets.lock().unwrap().insert(widget.id().to_owned(), widget.clone());
+        Ok(widget)
+    }
+
+    /// Look up a widget by its model id
+    pub fn get(&self, id: &str) -> Option<Widget> {
+        self.widgets.lock().unwrap().get(id).cloned()
+    }
+
+    /// Every widget which is alive
+    pub fn all(&self) -> Vec<Widget> {
+        self.widgets.lock().unwrap().values().cloned().collect()
+    }
+
+    /// Forget every widget, e.g. when the kernel restarts.
+    ///
+    /// Observers and custom message handlers are dropped too, since they often hold on to
+    /// widgets themselves and would otherwise keep them, and their comms, alive forever.
+    pub fn clear(&self) {
+        for (_, widget) in self.widgets.lock().unwrap().drain() {
+            widget.inner.observers.lock().unwrap().clear();
+            widget.inner.custom_handlers.lock().unwrap().clear();
+        }
+    }
+}
+
+/// Show `controls` above an [Output] widget, and call `function` with the current values of the
+/// controls (see [Widget::value]) whenever one of them changes, and once straight away.
+///
+/// `function` returns a mime bundle which replaces the contents of the output. If it fails, the
+/// error is shown instead. Returns the box holding the controls and the output; display it with
+/// [Widget::mime_bundle].
+pub fn interact(
+    widgets: &Widgets,
+    controls: Vec<(String, Widget)>,
+    function: impl Fn(&Map<String, Value>) -> Result<Value> + Send + Sync + 'static,
+) -> Result<Widget> {
+    let output = widgets.create(&Output::default())?;
+    let run = Arc::new({
+        // Each control owns an observer holding `run`, so `run` must not own the controls
+        let controls: Vec<(String, Weak<WidgetInner>)> = controls
+            .iter()
+            .map(|(name, control)| (name.clone(), Arc::downgrade(&control.inner)))
+            .collect();
+        let output = output.clone();
+        move || -> Result<()> {
+            let arguments = controls
+                .iter()
+                .filter_map(|(name, control)| {
+                    let control = Widget { inner: control.upgrade()? };
+                    Some((name.clone(), control.value().unwrap_or_default()))
+                })
+                .collect();
+            let result = match function(&arguments) {
+                Ok(data) => json!({"output_type": "display_data", "data": data, "metadata": {}}),
+                Err(err) => json!({"output_type": "stream", "name": "stderr", "text": format!("{err:#}\n")}),
+            };
+            output.set("outputs", json!([result]))
+        }
+    });
+    for (_, control) in &controls {
+        let run = run.clone();
+        control.observe(move |_, changes| {
+            if changes.contains_key("value") || changes.contains_key("index") {
+                run()?;
+            }
+            Ok(())
+        });
+    }
+    run()?;
+    let children: Vec<&Widget> = controls.iter().map(|(_, control)| control).chain([&output]).collect();
+    widgets.create(&VBox::new(&children))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        protocol::{CommMsg, CommOpen, MessageContent, MessageParsed},
+        server::{Iopub, Session},
+    };
+    use models::IntSlider;
+    use tokio::sync::mpsc::UnboundedReceiver;
+
+    fn comm_msgs(published: &mut UnboundedReceiver<MessageParsed>) -> Vec<Map<String, Value>> {
+        std::iter::from_fn(|| published.try_recv().ok())
+            .filter_map(|message| match message.content {
+                MessageContent::CommMsg(CommMsg { data, .. }) => Some(data),
+                _ => None,
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_interact_reruns_on_update() {
+        let (iopub, mut published) = Iopub::new(Session::new(Default::default()), Default::default());
+        let comms = Comms::new(iopub);
+        let widgets = Widgets::register(&comms);
+
+        let slider = widgets.create(&IntSlider { value: 3, ..Default::default() }).unwrap();
+        assert_eq!(slider.model::<IntSlider>().unwrap().value, 3);
+        let output_id = |vbox: &Widget| {
+            let children = vbox.get("children").unwrap();
+            children[1].as_str().unwrap().trim_start_matches("IPY_MODEL_").to_owned()
+        };
+        let vbox = interact(&widgets, vec![("x".into(), slider.clone())], |arguments| {
+            Ok(json!({"text/plain": format!("x = {}", arguments["x"])}))
+        }).unwrap();
+        let output = widgets.get(&output_id(&vbox)).unwrap();
+        assert_eq!(output.get("outputs").unwrap()[0]["data"]["text/plain"], "x = 3");
+        comm_msgs(&mut published);
+
+        // the frontend moves the slider
+        let mut data = Map::new();
+        data.insert("method".into(), "update".into());
+        data.insert("state".into(), json!({"value": 7}));
+        data.insert("buffer_paths".into(), json!([]));
+        comms.handle_message(&CommMsg { comm_id: slider.id().into(), data }, &[]).unwrap();
+
+        assert_eq!(output.get("outputs").unwrap()[0]["data"]["text/plain"], "x = 7");
+        let sent = comm_msgs(&mut published);
+        assert_eq!(sent[0]["method"], "echo_update");
+        assert_eq!(sent[1]["state"]["outputs"][0]["data"]["text/plain"], "x = 7");
+    }
+
+    #[test]
+    fn test_interact_does_not_leak() {
+        let (iopub, mut published) = Iopub::new(Session::new(Default::default()), Default::default());
+        let comms = Comms::new(iopub);
+        let widgets = Widgets::register(&comms);
+
+        let slider = widgets.create(&IntSlider::default()).unwrap();
+        let vbox = interact(&widgets, vec![("x".into(), slider.clone())], |arguments| {
+            Ok(json!({"text/plain": arguments["x"].to_string()}))
+        }).unwrap();
+        let button = widgets.create(&models::Button::default()).unwrap();
+        button.on_click({
+            let button = button.clone();
+            move |_| button.set("description", "clicked")
+        });
+
+        // what the server does when it shuts down
+        widgets.clear();
+        comms.close_all();
+        drop((slider, vbox, button, widgets, comms));
+
+        while published.try_recv().is_ok() {}
+        assert!(matches!(published.try_recv(), Err(tokio::sync::mpsc::error::TryRecvError::Disconnected)));
+    }
+
+    #[test]
+    fn test_request_states() {
+        let (iopub, mut published) = Iopub::new(Session::new(Default::default()), Default::default());
+        let comms = Comms::new(iopub);
+        let widgets = Widgets::register(&comms);
+
+        let slider = widgets.create(&IntSlider { value: 3, ..Default::default() }).unwrap();
+        let mut state = Map::new();
+        state.insert("_model_name".into(), "ImageModel".into());
+        state.insert("_model_module".into(), CONTROLS_MODULE.into());
+        state.insert("_model_module_version".into(), CONTROLS_MODULE_VERSION.into());
+        let image = widgets.create_from_state(state, vec![(vec!["value".into()], Bytes::from_static(b"png"))]).unwrap();
+        while published.try_recv().is_ok() {}
+
+        comms.handle_open(&CommOpen {
+            comm_id: "control".into(),
+            target_name: CONTROL_TARGET.into(),
+            data: json!({}),
+            target_module: None,
+        }, &[]).unwrap();
+        let mut data = Map::new();
+        data.insert("method".into(), "request_states".into());
+        comms.handle_message(&CommMsg { comm_id: "control".into(), data }, &[]).unwrap();
+
+        let reply = std::iter::from_fn(|| published.try_recv().ok())
+            .find(|message| matches!(message.content, MessageContent::CommMsg(_)))
+            .unwrap();
+        let MessageContent::CommMsg(CommMsg { data, .. }) = &reply.content else { unreachable!() };
+        assert_eq!(data["method"], "update_states");
+        assert_eq!(data["states"][slider.id()]["model_name"], "IntSliderModel");
+        assert_eq!(data["states"][slider.id()]["model_module"], CONTROLS_MODULE);
+        assert_eq!(data["states"][slider.id()]["model_module_version"], CONTROLS_MODULE_VERSION);
+        assert_eq!(data["states"][slider.id()]["state"]["value"], 3);
+        assert_eq!(data["states"][image.id()]["model_name"], "ImageModel");
+        assert_eq!(data["buffer_paths"], json!([[image.id(), "state", "value"]]));
+        assert_eq!(reply.extra_buffers, [Bytes::from_static(b"png")]);
+    }
+}
@@ -0,0 +1,286 @@
+//! The state of the core widgets, matching the models in `@jupyter-widgets/controls` 2.0.0 and
+//! `@jupyter-widgets/output` 1.0.0 (ipywidgets 8).
+//!
+//! Only the most useful attributes are listed; the frontend fills in defaults for the rest.
+
+use super::Widget;
+
+use serde::{Deserialize, Serialize};
+
+pub const BASE_MODULE: &str = "@jupyter-widgets/base";
+pub const BASE_MODULE_VERSION: &str = "2.0.0";
+pub const CONTROLS_MODULE: &str = "@jupyter-widgets/controls";
+pub const CONTROLS_MODULE_VERSION: &str = "2.0.0";
+pub const OUTPUT_MODULE: &str = "@jupyter-widgets/output";
+pub const OUTPUT_MODULE_VERSION: &str = "1.0.0";
+
+/// A kind of widget. The fields of the implementing type are the synced state of the widget.
+pub trait WidgetModel: Serialize {
+    const MODEL_NAME: &'static str;
+    const MODEL_MODULE: &'static str = CONTROLS_MODULE;
+    const MODEL_MODULE_VERSION: &'static str = CONTROLS_MODULE_VERSION;
+    const VIEW_NAME: &'static str;
+    const VIEW_MODULE: &'static str = Self::MODEL_MODULE;
+    const VIEW_MODULE_VERSION: &'static str = Self::MODEL_MODULE_VERSION;
+    /// The model of the `style` widget created along with this widget, if it has one
+    const STYLE_MODEL_NAME: Option<&'static str> = None;
+    /// Whether this widget is displayed, and so needs a `layout` widget
+    const HAS_LAYOUT: bool = true;
+}
+
+/// The CSS layout of a widget. One is created for every widget which can be displayed.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Default)]
+pub struct Layout {}
+
+impl WidgetModel for Layout {
+    const MODEL_NAME: &'static str = "LayoutModel";
+    const MODEL_MODULE: &'static str = BASE_MODULE;
+    const MODEL_MODULE_VERSION: &'static str = BASE_MODULE_VERSION;
+    const VIEW_NAME: &'static str = "LayoutView";
+    const HAS_LAYOUT: bool = false;
+}
+
+/// A slider for whole numbers
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+#[serde(default)]
+pub struct IntSlider {
+    pub value: i64,
+    pub min: i64,
+    pub max: i64,
+    pub step: i64,
+    pub description: String,
+    pub disabled: bool,
+    /// Update the value while the slider is being dragged, rather than only when it is released
+    pub continuous_update: bool,
+    /// `horizontal` or `vertical`
+    pub orientation: String,
+    /// Show the value next to the slider
+    pub readout: bool,
+    pub readout_format: String,
+}
+
+impl Default for IntSlider {
+    fn default() -> Self {
+        Self {
+            value             : 0,
+            min               : 0,
+            max               : 100,
+            step              : 1,
+            description       : String::new(),
+            disabled          : false,
+            continuous_update : true,
+            orientation       : "horizontal".into(),
+            readout           : true,
+            readout_format    : "d".into(),
+        }
+    }
+}
+
+impl WidgetModel for IntSlider {
+    const MODEL_NAME: &'static str = "IntSliderModel";
+    const VIEW_NAME: &'static str = "IntSliderView";
+    const STYLE_MODEL_NAME: Option<&'static str> = Some("SliderStyleModel");
+}
+
+/// A slider for decimal numbers
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+#[serde(default)]
+pub struct FloatSlider {
+    pub value: f64,
+    pub min: f64,
+    pub max: f64,
+    pub step: f64,
+    pub description: String,
+    pub disabled: bool,
+    /// Update the value while the slider is being dragged, rather than only when it is released
+    pub continuous_update: bool,
+    /// `horizontal` or `vertical`
+    pub orientation: String,
+    /// Show the value next to the slider
+    pub readout: bool,
+    pub readout_format: String,
+}
+
+impl Default for FloatSlider {
+    fn default() -> Self {
+        Self {
+            value             : 0.0,
+            min               : 0.0,
+            max               : 10.0,
+            step              : 0.1,
+            description       : String::new(),
+            disabled          : false,
+            continuous_update : true,
+            orientation       : "horizontal".into(),
+            readout           : true,
+            readout_format    : ".2f".into(),
+        }
+    }
+}
+
+impl WidgetModel for FloatSlider {
+    const MODEL_NAME: &'static str = "FloatSliderModel";
+    const VIEW_NAME: &'static str = "FloatSliderView";
+    const STYLE_MODEL_NAME: Option<&'static str> = Some("SliderStyleModel");
+}
+
+/// A single line text box
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+#[serde(default)]
+pub struct Text {
+    pub value: String,
+    /// Shown in grey while the text box is empty
+    pub placeholder: String,
+    pub description: String,
+    pub disabled: bool,
+    /// Update the value on every key press, rather than when the text box loses focus
+    pub continuous_update: bool,
+}
+
+impl Default for Text {
+    fn default() -> Self {
+        Self {
+            value             : String::new(),
+            placeholder       : "\u{200b}".into(),
+            description       : String::new(),
+            disabled          : false,
+            continuous_update : true,
+        }
+    }
+}
+
+impl WidgetModel for Text {
+    const MODEL_NAME: &'static str = "TextModel";
+    const VIEW_NAME: &'static str = "TextView";
+    const STYLE_MODEL_NAME: Option<&'static str> = Some("TextStyleModel");
+}
+
+/// A button; use [Widget::on_click] to respond to clicks
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Default)]
+#[serde(default)]
+pub struct Button {
+    pub description: String,
+    /// One of `primary`, `success`, `info`, `warning`, `danger` or empty for the default style
+    pub button_style: String,
+    /// The name of a font awesome icon, e.g. `check`
+    pub icon: String,
+    pub tooltip: Option<String>,
+    pub disabled: bool,
+}
+
+impl WidgetModel for Button {
+    const MODEL_NAME: &'static str = "ButtonModel";
+    const VIEW_NAME: &'static str = "ButtonView";
+    const STYLE_MODEL_NAME: Option<&'static str> = Some("ButtonStyleModel");
+}
+
+/// A tick box
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+#[serde(default)]
+pub struct Checkbox {
+    pub value: bool,
+    pub description: String,
+    pub disabled: bool,
+    /// Line the checkbox up with the other widgets which have a description
+    pub indent: bool,
+}
+
+impl Default for Checkbox {
+    fn default() -> Self {
+        Self {
+            value       : false,
+            description : String::new(),
+            disabled    : false,
+            indent      : true,
+        }
+    }
+}
+
+impl WidgetModel for Checkbox {
+    const MODEL_NAME: &'static str = "CheckboxModel";
+    const VIEW_NAME: &'static str = "CheckboxView";
+    const STYLE_MODEL_NAME: Option<&'static str> = Some("CheckboxStyleModel");
+}
+
+/// Choose one of several options from a drop down list
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Default)]
+#[serde(default)]
+pub struct Dropdown {
+    #[serde(rename = "_options_labels")]
+    pub options: Vec<String>,
+    /// The index of the selected option, if any
+    pub index: Option<usize>,
+    pub description: String,
+    pub disabled: bool,
+}
+
+impl WidgetModel for Dropdown {
+    const MODEL_NAME: &'static str = "DropdownModel";
+    const VIEW_NAME: &'static str = "DropdownView";
+    const STYLE_MODEL_NAME: Option<&'static str> = Some("DescriptionStyleModel");
+}
+
+/// Shows a list of outputs, in the same format as the outputs of a notebook cell; see
+/// [Widget::append_output]
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Default)]
+#[serde(default)]
+pub struct Output {
+    pub outputs: Vec<serde_json::Value>,
+    /// The id of a message whose outputs the frontend should capture in this widget
+    pub msg_id: String,
+}
+
+impl WidgetModel for Output {
+    const MODEL_NAME: &'static str = "OutputModel";
+    const MODEL_MODULE: &'static str = OUTPUT_MODULE;
+    const MODEL_MODULE_VERSION: &'static str = OUTPUT_MODULE_VERSION;
+    const VIEW_NAME: &'static str = "OutputView";
+}
+
+/// Lays out its children in a row
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Default)]
+#[serde(default)]
+pub struct HBox {
+    /// References to the child widgets, see [Widget::reference]
+    pub children: Vec<String>,
+    /// One of `success`, `info`, `warning`, `danger` or empty for no border
+    pub box_style: String,
+}
+
+impl HBox {
+    pub fn new(children: &[&Widget]) -> Self {
+        Self {
+            children: children.iter().map(|child| child.reference()).collect(),
+            ..Default::default()
+        }
+    }
+}
+
+impl WidgetModel for HBox {
+    const MODEL_NAME: &'static str = "HBoxModel";
+    const VIEW_NAME: &'static str = "HBoxView";
+}
+
+/// Lays out its children in a column
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Default)]
+#[serde(default)]
+pub struct VBox {
+    /// References to the child widgets, see [Widget::reference]
+    pub children: Vec<String>,
+    /// One of `success`, `info`, `warning`, `danger` or empty for no border
+    pub box_style: String,
+}
+
+impl VBox {
+    pub fn new(children: &[&Widget]) -> Self {
+        Self {
+            children: children.iter().map(|child| child.reference()).collect(),
+            ..Default::default()
+        }
+    }
+}
+
+impl WidgetModel for VBox {
+    const MODEL_NAME: &'static str = "VBoxModel";
+    const VIEW_NAME: &'static str = "VBoxView";
+}